@@ -1,6 +1,17 @@
-use chrono::{Duration, Local, NaiveDate};
+use chrono::{Datelike, Duration, Local, NaiveDate};
 use cursive::direction::Absolute;
 
+// `date` moved by `months` calendar months, the day of month is
+// clamped to the length of the month it lands in
+pub fn add_months(date: NaiveDate, months: i32) -> NaiveDate {
+    let total = date.year() * 12 + date.month0() as i32 + months;
+    let (year, month) = (total.div_euclid(12), total.rem_euclid(12) as u32 + 1);
+    return (1..=date.day())
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .unwrap_or(date);
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct Cursor(pub NaiveDate);
 
@@ -48,22 +59,22 @@ impl Cursor {
             Absolute::None => {}
         }
     }
-    fn long_seek(&mut self, offset: Duration) {
-        let cursor = self.0;
+    // moves to `date`, dates past today stop at today
+    pub fn seek(&mut self, date: NaiveDate) {
         let today = Local::now().naive_local().date();
-        let next = cursor.checked_add_signed(offset).unwrap_or(cursor);
-
-        if next <= today {
-            self.0 = next;
-        } else {
-            self.0 = today;
-        }
+        self.0 = date.min(today);
     }
     pub fn month_forward(&mut self) {
-        self.long_seek(Duration::weeks(4));
+        self.seek(add_months(self.0, 1));
     }
     pub fn month_backward(&mut self) {
-        self.long_seek(Duration::weeks(-4));
+        self.seek(add_months(self.0, -1));
+    }
+    pub fn year_forward(&mut self) {
+        self.seek(add_months(self.0, 12));
+    }
+    pub fn year_backward(&mut self) {
+        self.seek(add_months(self.0, -12));
     }
     pub fn reset(&mut self) {
         self.0 = Local::now().naive_local().date();
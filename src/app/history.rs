@@ -0,0 +1,221 @@
+use chrono::NaiveDate;
+
+use crate::app::{App, MessageKind};
+use crate::command::Goal;
+use crate::habit::{HabitWrapper, Schedule};
+
+// A change to the habits, as recorded in the history. Applying a
+// change returns the change that reverts it.
+pub enum Change {
+    // set the entry of a habit on a date, `None` clears it
+    Entry(String, NaiveDate, Option<String>), // habit, date, value
+    Rename(String, String),                   // from, to
+    Archive(String, bool),                    // habit, archived
+    Goal(String, Goal),                       // habit, goal
+    Schedule(String, Schedule),               // habit, schedule
+    Excuse(String, NaiveDate, bool),          // habit, date, excused
+    Note(String, NaiveDate, Option<String>),  // habit, date, note
+    Remove(String),                           // habit
+    Insert(usize, Box<dyn HabitWrapper>),     // position, habit
+}
+
+// Undo and redo stacks for the session. Every step is the list of
+// changes that reverts a single command or keypress, these are
+// applied last to first.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Vec<Change>>,
+    redo: Vec<Vec<Change>>,
+}
+
+impl History {
+    pub fn record(&mut self, step: Vec<Change>) {
+        if step.is_empty() {
+            return;
+        }
+        self.undo.push(step);
+        self.redo.clear();
+    }
+}
+
+impl App {
+    fn apply(&mut self, change: Change) -> Option<Change> {
+        let (revert, auto) = match change {
+            Change::Entry(name, date, value) => {
+                let h = self.habits.iter_mut().find(|h| h.name() == name)?;
+                let before = h.value(date);
+                match value {
+                    Some(v) => {
+                        h.set_value(date, &v);
+                    }
+                    None => h.remove_entry(date),
+                }
+                (Change::Entry(name, date, before), h.is_auto())
+            }
+            Change::Rename(from, to) => {
+                let h = self.habits.iter_mut().find(|h| h.name() == from)?;
+                h.set_name(&to);
+                (Change::Rename(to, from), h.is_auto())
+            }
+            Change::Archive(name, archived) => {
+                let h = self.habits.iter_mut().find(|h| h.name() == name)?;
+                h.set_archived(archived);
+                (Change::Archive(name, !archived), h.is_auto())
+            }
+            Change::Goal(name, goal) => {
+                let h = self.habits.iter_mut().find(|h| h.name() == name)?;
+                let before = h.goal_expr();
+                h.set_goal_kind(goal.kind);
+                h.set_period(goal.period);
+                (Change::Goal(name, before), h.is_auto())
+            }
+            Change::Schedule(name, schedule) => {
+                let h = self.habits.iter_mut().find(|h| h.name() == name)?;
+                let before = h.schedule();
+                h.set_schedule(schedule);
+                (Change::Schedule(name, before), h.is_auto())
+            }
+            Change::Excuse(name, date, excused) => {
+                let h = self.habits.iter_mut().find(|h| h.name() == name)?;
+                h.set_excused(date, excused);
+                (Change::Excuse(name, date, !excused), h.is_auto())
+            }
+            Change::Note(name, date, note) => {
+                let h = self.habits.iter_mut().find(|h| h.name() == name)?;
+                let before = h.note(date);
+                h.set_note(date, note);
+                (Change::Note(name, date, before), h.is_auto())
+            }
+            Change::Remove(name) => {
+                let idx = self.habits.iter().position(|h| h.name() == name)?;
+                let h = self.habits.remove(idx);
+                let auto = h.is_auto();
+                (Change::Insert(idx, h), auto)
+            }
+            Change::Insert(idx, h) => {
+                let (name, auto) = (h.name(), h.is_auto());
+                self.habits.insert(idx.min(self.habits.len()), h);
+                (Change::Remove(name), auto)
+            }
+        };
+        self.mark_modified(auto);
+        return Some(revert);
+    }
+
+    fn apply_step(&mut self, step: Vec<Change>) -> Vec<Change> {
+        let reverts = step
+            .into_iter()
+            .rev()
+            .filter_map(|c| self.apply(c))
+            .collect::<Vec<_>>();
+        self.focus = self.focus.min(self.visible_count().saturating_sub(1));
+        return reverts;
+    }
+
+    pub fn record(&mut self, step: Vec<Change>) {
+        self.history.record(step);
+    }
+
+    pub fn undo(&mut self) {
+        match self.history.undo.pop() {
+            Some(step) => {
+                let redo = self.apply_step(step);
+                self.history.redo.push(redo);
+                self.message.set_kind(MessageKind::Info);
+                self.message.set_message("Undid last change");
+            }
+            None => {
+                self.message.set_kind(MessageKind::Error);
+                self.message.set_message("Nothing to undo");
+            }
+        }
+    }
+
+    pub fn redo(&mut self) {
+        match self.history.redo.pop() {
+            Some(step) => {
+                let undo = self.apply_step(step);
+                self.history.undo.push(undo);
+                self.message.set_kind(MessageKind::Info);
+                self.message.set_message("Redid last change");
+            }
+            None => {
+                self.message.set_kind(MessageKind::Error);
+                self.message.set_message("Nothing to redo");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::Command;
+
+    fn run(app: &mut App, command: &str) {
+        app.parse_command(Command::from_string(command));
+        assert!(
+            !matches!(app.message.kind(), MessageKind::Error),
+            "`{}` failed: {}",
+            command,
+            app.message.contents()
+        );
+    }
+
+    fn snapshot(app: &App) -> String {
+        return serde_json::to_string(&app.habits).unwrap();
+    }
+
+    #[test]
+    fn every_change_round_trips() {
+        let mut app = App::new();
+        run(&mut app, "add-auto walk 3");
+        run(&mut app, "add read 1");
+        let before = snapshot(&app);
+
+        let commands = [
+            "track-up walk",
+            "track-set walk 2 --date 2020-01-01",
+            "rename walk run",
+            "archive read",
+            "goal run 5/w",
+            "schedule run weekends",
+            "excuse run --date 2020-01-02",
+            "note run --date 2020-01-01 a long walk",
+            "delete read",
+            "add swim 1",
+        ];
+        for c in &commands {
+            run(&mut app, c);
+        }
+        let after = snapshot(&app);
+
+        for _ in &commands {
+            app.undo();
+        }
+        assert_eq!(snapshot(&app), before);
+        for _ in &commands {
+            app.redo();
+        }
+        assert_eq!(snapshot(&app), after);
+    }
+
+    #[test]
+    fn unchanged_values_are_not_recorded() {
+        let mut app = App::new();
+        run(&mut app, "add-auto walk 3");
+        run(&mut app, "track-set walk 2");
+        app.auto_modified = false;
+        run(&mut app, "track-up walk --by 0");
+        run(&mut app, "track-down walk --date 2020-01-01");
+        run(&mut app, "track-set walk 2");
+        assert!(!app.auto_modified);
+
+        // only adding and tracking walk can be undone
+        app.undo();
+        app.undo();
+        app.undo();
+        assert!(app.habits.is_empty());
+        assert!(matches!(app.message.kind(), MessageKind::Error));
+    }
+}
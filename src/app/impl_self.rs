@@ -1,8 +1,8 @@
-use std::default::Default;
 use std::f64;
-use std::fs::{File, OpenOptions};
-use std::io::prelude::*;
-use std::path::PathBuf;
+use std::fs::{self, File};
+use std::io::{self, prelude::*};
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::mpsc::channel;
 use std::time::Duration;
 
@@ -11,17 +11,115 @@ use cursive::direction::Absolute;
 use cursive::Vec2;
 use notify::{watcher, RecursiveMode, Watcher};
 
-use crate::command::{Command, CommandLineError, GoalKind};
-use crate::habit::{Bit, Count, Float, HabitWrapper, TrackEvent, ViewMode};
-use crate::utils::{self, GRID_WIDTH, VIEW_HEIGHT, VIEW_WIDTH};
+use crate::command::{Command, CommandLineError, Goal, GoalKind};
+use crate::export::{self, ExportFormat};
+use crate::habit::{
+    Addiction, Bit, Count, Float, HabitWrapper, Schedule, Stats, TrackEvent, ViewMode,
+};
+use crate::import::{self, Conflict, ImportError, ImportReport, Record};
+use crate::utils::{self, VIEW_HEIGHT, VIEW_WIDTH};
 
-use crate::app::{App, Cursor, Message, MessageKind, StatusLine};
+use crate::app::{App, Change, Cursor, Message, MessageKind, StatusLine};
+
+fn new_habit(name: &str, goal: Option<Goal>, auto: bool) -> Box<dyn HabitWrapper> {
+    let mut habit: Box<dyn HabitWrapper> = match goal.map(|g| g.kind) {
+        Some(GoalKind::Bit) => Box::new(Bit::new(name, auto)),
+        Some(GoalKind::Count(v)) => Box::new(Count::new(name, v, auto)),
+        Some(GoalKind::Float(v, p)) => Box::new(Float::new(name, v, p, auto)),
+        Some(GoalKind::Addiction(v)) => Box::new(Addiction::new(name, v, auto)),
+        None => Box::new(Count::new(name, 0, auto)),
+    };
+    if let Some(g) = goal {
+        habit.set_period(g.period);
+    }
+    return habit;
+}
+
+// The goal of a habit that can hold every imported value, this is
+// the largest value, read as a goal expression. Yes/no values make
+// a bit habit. Returns the line and value that could not be read.
+fn infer_goal<'a>(records: impl Iterator<Item = &'a Record>) -> Result<GoalKind, (usize, String)> {
+    let (mut largest, mut precision) = (0f64, None);
+    for r in records {
+        let goal = match r.value.to_lowercase().as_ref() {
+            "y" | "yes" | "true" => GoalKind::Bit,
+            "n" | "no" | "false" => GoalKind::Count(0),
+            v => GoalKind::from_str(v).map_err(|_| (r.line, r.value.clone()))?,
+        };
+        let value = match goal {
+            GoalKind::Bit => 1.,
+            GoalKind::Count(n) => n as f64,
+            GoalKind::Float(v, p) => {
+                precision = precision.max(Some(p));
+                v as f64 / 10f64.powi(p as i32)
+            }
+            GoalKind::Addiction(_) => return Err((r.line, r.value.clone())),
+        };
+        largest = largest.max(value);
+    }
+    match precision {
+        Some(p) => {
+            let scaled = (largest * 10f64.powi(p as i32)).round() as u32;
+            return Ok(GoalKind::Float(scaled, p));
+        }
+        None if largest <= 1. => return Ok(GoalKind::Bit),
+        None => return Ok(GoalKind::Count(largest as u32)),
+    }
+}
+
+// habits read from a data file, and the error if it was recovered
+type Recovered = (Vec<Box<dyn HabitWrapper>>, Option<String>);
+
+// Like `App::read_habits`, but a corrupt `file` is set aside and
+// the newest readable backup is used in its place. The error is
+// returned along with the recovered habits. If `file` cannot be
+// set aside, nothing is recovered and an error is returned.
+fn recover_habits(file: &Path) -> Result<Recovered, String> {
+    let err = match App::read_habits(file) {
+        Ok(habits) => return Ok((habits, None)),
+        Err(e) => e,
+    };
+    let mut corrupt = file.as_os_str().to_owned();
+    corrupt.push(".corrupt");
+    if let Err(e) = fs::copy(file, &corrupt) {
+        return Err(format!(
+            "`{}` is corrupt ({}) and could not be copied to `{}` ({}), it will not be written",
+            file.display(),
+            err,
+            Path::new(&corrupt).display(),
+            e
+        ));
+    }
+
+    for backup in utils::backups_of(file) {
+        if let Ok(habits) = App::read_habits(&backup) {
+            let message = format!(
+                "`{}` is corrupt ({}), restored from backup `{}`",
+                file.display(),
+                err,
+                backup.display()
+            );
+            return Ok((habits, Some(message)));
+        }
+    }
+    let message = format!(
+        "`{}` is corrupt ({}) and no backup could be read, a copy was kept at `{}`",
+        file.display(),
+        err,
+        Path::new(&corrupt).display()
+    );
+    return Ok((Vec::new(), Some(message)));
+}
 
 impl App {
     pub fn new() -> Self {
         let (tx, rx) = channel();
         let mut watcher = watcher(tx, Duration::from_secs(1)).unwrap();
-        watcher.watch(utils::auto_habit_file(), RecursiveMode::Recursive);
+        // data files are replaced by renaming a new file over the
+        // old one, so watch the directory rather than the files
+        if let Some(data_dir) = utils::habit_file().parent() {
+            watcher.watch(data_dir, RecursiveMode::NonRecursive);
+        }
         return App {
             habits: vec![],
             focus: 0,
@@ -29,9 +127,43 @@ impl App {
             file_event_recv: rx,
             cursor: Cursor::new(),
             message: Message::startup(),
+            history: Default::default(),
+            size: Vec2::zero(),
+            columns: 1,
+            scroll: 0,
+            regular_modified: false,
+            auto_modified: false,
+            corrupt: Vec::new(),
+            regular_on_disk: String::new(),
+            auto_on_disk: String::new(),
         };
     }
 
+    // note unsaved changes to regular or auto habits, only
+    // modified data files are written by `save_state`
+    pub fn mark_modified(&mut self, auto: bool) {
+        if auto {
+            self.auto_modified = true;
+        } else {
+            self.regular_modified = true;
+        }
+    }
+
+    // both data files are written by `dijo -c` and `--import` as
+    // well, so the interactive program saves its changes right away
+    // instead of on quit, and reloads files changed by others
+    pub fn save_changes(&mut self) {
+        if !self.regular_modified && !self.auto_modified {
+            return;
+        }
+        let saved = utils::DataLock::exclusive().and_then(|_lock| self.save_state());
+        if let Err(e) = saved {
+            self.message.set_kind(MessageKind::Error);
+            self.message
+                .set_message(format!("Unable to save habits: {}", e));
+        }
+    }
+
     pub fn add_habit(&mut self, h: Box<dyn HabitWrapper>) {
         self.habits.push(h);
     }
@@ -40,44 +172,392 @@ impl App {
         self.habits.iter().map(|x| x.name()).collect::<Vec<_>>()
     }
 
-    pub fn delete_by_name(&mut self, name: &str) {
-        let old_len = self.habits.len();
-        self.habits.retain(|h| h.name() != name);
-        if old_len == self.habits.len() {
+    // (name, current streak, longest streak) of every habit that is,
+    // or is not, archived
+    pub fn list_streaks(&self, archived: bool) -> Vec<(String, u32, u32)> {
+        self.habits
+            .iter()
+            .filter(|x| x.is_archived() == archived)
+            .map(|x| (x.name(), x.current_streak(), x.longest_streak()))
+            .collect::<Vec<_>>()
+    }
+
+    pub fn habit_stats(&self, name: &str) -> Option<(Goal, Stats)> {
+        let h = self.habits.iter().find(|h| h.name() == name)?;
+        return Some((h.goal_expr(), h.stats()));
+    }
+
+    // write every tracked entry to `out`, returns the number of
+    // entries written
+    pub fn export(&self, format: ExportFormat, out: &mut impl Write) -> io::Result<usize> {
+        let rows = export::rows(&self.habits);
+        export::write_rows(format, &rows, out)?;
+        out.flush()?;
+        return Ok(rows.len());
+    }
+
+    // Import `records`, adding habits that do not exist yet. Nothing
+    // is imported if any value does not fit its habit. Values that
+    // differ from already tracked ones are reported, not imported.
+    pub fn import(&mut self, records: Vec<Record>) -> Result<ImportReport, ImportError> {
+        let mut report = ImportReport::default();
+        let mut new_habits: Vec<Box<dyn HabitWrapper>> = Vec::new();
+        for r in &records {
+            if self
+                .habits
+                .iter()
+                .chain(&new_habits)
+                .any(|h| h.name() == r.habit)
+            {
+                continue;
+            }
+            let same_habit = records.iter().filter(|x| x.habit == r.habit);
+            let goal = match records
+                .iter()
+                .find_map(|x| x.goal.as_ref().filter(|_| x.habit == r.habit))
+            {
+                Some(g) => Goal::from_str(g)
+                    .map_err(|_| ImportError::Parse(r.line, format!("invalid goal `{}`", g)))?,
+                None => Goal {
+                    kind: infer_goal(same_habit).map_err(|(line, v)| {
+                        ImportError::Parse(line, format!("invalid value `{}`", v))
+                    })?,
+                    period: Default::default(),
+                },
+            };
+            new_habits.push(new_habit(&r.habit, Some(goal), false));
+            report.created.push(r.habit.clone());
+        }
+
+        let created = self.habits.len();
+        self.habits.extend(new_habits);
+        let mut entries = Vec::new();
+        for r in &records {
+            let idx = self
+                .habits
+                .iter()
+                .position(|h| h.name() == r.habit)
+                .unwrap();
+            match self.habits[idx].normalize_value(&r.value) {
+                Some(v) => entries.push((idx, r.date, v)),
+                None => {
+                    self.habits.truncate(created);
+                    return Err(ImportError::Parse(
+                        r.line,
+                        format!("invalid value `{}` for habit `{}`", r.value, r.habit),
+                    ));
+                }
+            }
+        }
+
+        // new habits are removed last when undoing
+        let mut step = report
+            .created
+            .iter()
+            .map(|name| Change::Remove(name.clone()))
+            .collect::<Vec<_>>();
+        for (idx, date, value) in entries {
+            let habit = &mut self.habits[idx];
+            match habit.value(date) {
+                Some(existing) if existing == value => {}
+                Some(existing) => report.conflicts.push(Conflict {
+                    habit: habit.name(),
+                    date,
+                    existing,
+                    imported: value,
+                }),
+                None => {
+                    habit.set_value(date, &value);
+                    step.push(Change::Entry(habit.name(), date, None));
+                    let auto = habit.is_auto();
+                    self.mark_modified(auto);
+                    report.imported += 1;
+                }
+            }
+        }
+        if !report.created.is_empty() {
+            self.mark_modified(false);
+        }
+        self.record(step);
+        return Ok(report);
+    }
+
+    pub fn delete_by_name(&mut self, name: &str) -> bool {
+        let idx = match self.habits.iter().position(|h| h.name() == name) {
+            Some(idx) => idx,
+            None => {
+                self.message.set_kind(MessageKind::Error);
+                self.message
+                    .set_message(format!("Could not delete habit `{}`", name));
+                return false;
+            }
+        };
+        let habit = self.habits.remove(idx);
+        self.mark_modified(habit.is_auto());
+        self.record(vec![Change::Insert(idx, habit)]);
+        return true;
+    }
+
+    pub fn rename_habit(&mut self, old: &str, new: &str) -> bool {
+        if self.habits.iter().any(|h| h.name() == new) {
+            self.message.set_kind(MessageKind::Error);
             self.message
-                .set_message(format!("Could not delete habit `{}`", name))
+                .set_message(format!("Habit `{}` already exist", new));
+            return false;
         }
+        let auto = match self.habits.iter_mut().find(|h| h.name() == old) {
+            Some(h) => {
+                h.set_name(new);
+                h.is_auto()
+            }
+            None => {
+                self.message.set_kind(MessageKind::Error);
+                self.message
+                    .set_message(format!("Could not rename habit `{}`", old));
+                return false;
+            }
+        };
+        self.mark_modified(auto);
+        self.record(vec![Change::Rename(new.into(), old.into())]);
+        return true;
+    }
+
+    pub fn set_goal(&mut self, name: &str, goal: Goal) -> bool {
+        let h = match self.habits.iter_mut().find(|h| h.name() == name) {
+            Some(h) => h,
+            None => {
+                self.message.set_kind(MessageKind::Error);
+                self.message
+                    .set_message(format!("Could not find habit `{}`", name));
+                return false;
+            }
+        };
+        let before = h.goal_expr();
+        if !h.set_goal_kind(goal.kind) {
+            self.message.set_kind(MessageKind::Error);
+            self.message.set_message(format!(
+                "Goal does not fit {} habit `{}`",
+                h.kind().name(),
+                name
+            ));
+            return false;
+        }
+        h.set_period(goal.period);
+        let auto = h.is_auto();
+        self.mark_modified(auto);
+        self.record(vec![Change::Goal(name.into(), before)]);
+        return true;
+    }
+
+    pub fn set_schedule(&mut self, name: &str, schedule: Schedule) -> bool {
+        let h = match self.habits.iter_mut().find(|h| h.name() == name) {
+            Some(h) => h,
+            None => {
+                self.message.set_kind(MessageKind::Error);
+                self.message
+                    .set_message(format!("Could not find habit `{}`", name));
+                return false;
+            }
+        };
+        let before = h.schedule();
+        h.set_schedule(schedule);
+        let auto = h.is_auto();
+        self.mark_modified(auto);
+        self.record(vec![Change::Schedule(name.into(), before)]);
+        return true;
+    }
+
+    // excuse `name` on `date`, or every visible habit if no name is
+    // given
+    pub fn set_excused(&mut self, name: Option<&str>, date: NaiveDate, excused: bool) {
+        if let Some(n) = name {
+            if !self.habits.iter().any(|h| h.name() == n) {
+                self.message.set_kind(MessageKind::Error);
+                self.message
+                    .set_message(format!("Could not find habit `{}`", n));
+                return;
+            }
+        }
+        let (mut step, mut autos) = (Vec::new(), Vec::new());
+        for h in self.habits.iter_mut() {
+            let selected = match name {
+                Some(n) => h.name() == n,
+                None => !h.is_archived(),
+            };
+            if !selected || h.is_excused(date) == excused {
+                continue;
+            }
+            h.set_excused(date, excused);
+            autos.push(h.is_auto());
+            step.push(Change::Excuse(h.name(), date, !excused));
+        }
+        for auto in autos {
+            self.mark_modified(auto);
+        }
+        self.record(step);
+    }
+
+    // toggles the excuse of the focused habit, or of every visible
+    // habit, on the cursor date
+    pub fn toggle_excused(&mut self, global: bool) {
+        let date = self.cursor.0;
+        let (name, excused) = if global {
+            let all = self.visible().all(|h| h.is_excused(date));
+            (None, !all)
+        } else {
+            match self.visible().nth(self.focus) {
+                Some(h) => (Some(h.name()), !h.is_excused(date)),
+                None => return,
+            }
+        };
+        self.set_excused(name.as_deref(), date, excused);
+        self.excuse_message(name.as_deref(), date, excused);
+    }
+
+    fn excuse_message(&mut self, name: Option<&str>, date: NaiveDate, excused: bool) {
+        let verb = if excused { "Excused" } else { "Unexcused" };
+        let target = match name {
+            Some(n) => format!("`{}`", n),
+            None => "every habit".into(),
+        };
+        self.message.set_kind(MessageKind::Info);
+        self.message
+            .set_message(format!("{} {} on {}", verb, target, date));
+    }
+
+    // attach a note to `name` on `date`, an empty note removes it
+    pub fn set_note(&mut self, name: &str, date: NaiveDate, note: &str) -> bool {
+        let h = match self.habits.iter_mut().find(|h| h.name() == name) {
+            Some(h) => h,
+            None => {
+                self.message.set_kind(MessageKind::Error);
+                self.message
+                    .set_message(format!("Could not find habit `{}`", name));
+                return false;
+            }
+        };
+        let note = Some(note.trim()).filter(|n| !n.is_empty());
+        let before = h.note(date);
+        if before.as_deref() == note {
+            return true;
+        }
+        self.message.set_kind(MessageKind::Info);
+        self.message.set_message(match note {
+            Some(_) => format!("Noted `{}` on {}", name, date),
+            None => format!("Cleared note of `{}` on {}", name, date),
+        });
+        h.set_note(date, note.map(String::from));
+        let auto = h.is_auto();
+        self.mark_modified(auto);
+        self.record(vec![Change::Note(name.into(), date, before)]);
+        return true;
+    }
+
+    // the focused habit, the cursor date and the note on it, if any
+    pub fn focused_note(&self) -> Option<(String, NaiveDate, Option<String>)> {
+        let date = self.cursor.0;
+        let h = self.visible().nth(self.focus)?;
+        return Some((h.name(), date, h.note(date)));
+    }
+
+    pub fn set_archived(&mut self, name: &str, archived: bool) -> bool {
+        let h = match self.habits.iter_mut().find(|h| h.name() == name) {
+            Some(h) => h,
+            None => {
+                self.message.set_kind(MessageKind::Error);
+                self.message
+                    .set_message(format!("Could not find habit `{}`", name));
+                return false;
+            }
+        };
+        if h.is_archived() == archived {
+            let state = if archived { "already" } else { "not" };
+            self.message.set_kind(MessageKind::Error);
+            self.message
+                .set_message(format!("Habit `{}` is {} archived", name, state));
+            return false;
+        }
+        h.set_archived(archived);
+        let auto = h.is_auto();
+        self.mark_modified(auto);
+        self.record(vec![Change::Archive(name.into(), !archived)]);
+        self.focus = self.focus.min(self.visible_count().saturating_sub(1));
+        return true;
+    }
+
+    // habits shown in the grid, archived habits are hidden
+    pub fn visible(&self) -> impl Iterator<Item = &Box<dyn HabitWrapper>> {
+        self.habits.iter().filter(|h| !h.is_archived())
+    }
+
+    pub fn visible_count(&self) -> usize {
+        self.visible().count()
+    }
+
+    pub fn focused_mut(&mut self) -> Option<&mut Box<dyn HabitWrapper>> {
+        self.habits
+            .iter_mut()
+            .filter(|h| !h.is_archived())
+            .nth(self.focus)
     }
 
     pub fn get_mode(&self) -> ViewMode {
-        if self.habits.is_empty() {
-            return ViewMode::Day;
+        match self.visible().nth(self.focus) {
+            Some(h) => return h.inner_data_ref().view_mode(),
+            None => return ViewMode::Day,
         }
-        return self.habits[self.focus].inner_data_ref().view_mode();
     }
 
     pub fn set_mode(&mut self, mode: ViewMode) {
-        if !self.habits.is_empty() {
-            self.habits[self.focus]
-                .inner_data_mut_ref()
-                .set_view_mode(mode);
+        if let Some(h) = self.focused_mut() {
+            h.inner_data_mut_ref().set_view_mode(mode);
         }
     }
 
-    pub fn sift_backward(&mut self) {
-        self.cursor.month_backward();
-        for v in self.habits.iter_mut() {
-            v.inner_data_mut_ref().cursor.month_backward();
+    // switch the focused habit to `mode`, or back to day mode if it
+    // is already in `mode`
+    pub fn toggle_mode(&mut self, mode: ViewMode) {
+        if self.get_mode() == mode {
+            self.set_mode(ViewMode::Day);
+        } else {
+            self.set_mode(mode);
         }
     }
 
-    pub fn sift_forward(&mut self) {
-        self.cursor.month_forward();
+    pub fn set_global_mode(&mut self, mode: ViewMode) {
+        for habit in self.habits.iter_mut() {
+            habit.inner_data_mut_ref().set_view_mode(mode);
+        }
+    }
+
+    // moves the cursor of the app and of every habit alike
+    fn seek(&mut self, f: impl Fn(&mut Cursor)) {
+        f(&mut self.cursor);
         for v in self.habits.iter_mut() {
-            v.inner_data_mut_ref().cursor.month_forward();
+            f(&mut v.inner_data_mut_ref().cursor);
         }
     }
 
+    pub fn sift_backward(&mut self) {
+        self.seek(Cursor::month_backward);
+    }
+
+    pub fn sift_forward(&mut self) {
+        self.seek(Cursor::month_forward);
+    }
+
+    pub fn sift_year_backward(&mut self) {
+        self.seek(Cursor::year_backward);
+    }
+
+    pub fn sift_year_forward(&mut self) {
+        self.seek(Cursor::year_forward);
+    }
+
+    pub fn goto(&mut self, date: NaiveDate) {
+        self.seek(|c| c.seek(date));
+    }
+
     pub fn reset_cursor(&mut self) {
         self.cursor.reset();
         for v in self.habits.iter_mut() {
@@ -93,9 +573,13 @@ impl App {
     }
 
     pub fn set_focus(&mut self, d: Absolute) {
+        let count = self.visible_count();
+        if count == 0 {
+            return;
+        }
         match d {
             Absolute::Right => {
-                if self.focus != self.habits.len() - 1 {
+                if self.focus != count - 1 {
                     self.focus += 1;
                 }
             }
@@ -105,15 +589,15 @@ impl App {
                 }
             }
             Absolute::Down => {
-                if self.focus + GRID_WIDTH < self.habits.len() - 1 {
-                    self.focus += GRID_WIDTH;
+                if self.focus + self.columns < count - 1 {
+                    self.focus += self.columns;
                 } else {
-                    self.focus = self.habits.len() - 1;
+                    self.focus = count - 1;
                 }
             }
             Absolute::Up => {
-                if self.focus as isize - GRID_WIDTH as isize >= 0 {
-                    self.focus -= GRID_WIDTH;
+                if self.focus >= self.columns {
+                    self.focus -= self.columns;
                 } else {
                     self.focus = 0;
                 }
@@ -124,12 +608,19 @@ impl App {
 
     pub fn clear_message(&mut self) {
         self.message.clear();
+        self.message.set_kind(MessageKind::Info);
+    }
+
+    pub fn message(&self) -> &Message {
+        &self.message
     }
 
     pub fn status(&self) -> StatusLine {
         let today = chrono::Local::now().naive_local().date();
-        let remaining = self.habits.iter().map(|h| h.remaining(today)).sum::<u32>();
-        let total = self.habits.iter().map(|h| h.goal()).sum::<u32>();
+        // habits that are off today have nothing to complete
+        let due = || self.visible().filter(|h| h.is_active(today));
+        let remaining = due().map(|h| h.remaining(today)).sum::<u32>();
+        let total = due().map(|h| h.goal()).sum::<u32>();
         let completed = total - remaining;
 
         let timestamp = if self.cursor.0 == today {
@@ -151,72 +642,211 @@ impl App {
         }
     }
 
-    pub fn max_size(&self) -> Vec2 {
-        let width = GRID_WIDTH * VIEW_WIDTH;
-        let height = {
-            if !self.habits.is_empty() {
-                (VIEW_HEIGHT as f64 * (self.habits.len() as f64 / GRID_WIDTH as f64).ceil())
-                    as usize
-            } else {
-                0
+    // habits per row of the grid, as laid out
+    pub fn columns(&self) -> usize {
+        return self.columns;
+    }
+
+    pub fn set_columns(&mut self, columns: usize) {
+        self.columns = columns.max(1);
+    }
+
+    // width of the grid, as laid out
+    pub fn grid_width(&self) -> usize {
+        return self.columns * (VIEW_WIDTH + 2);
+    }
+
+    // rows of habits in the grid
+    pub fn rows(&self) -> usize {
+        return self.visible_count().div_ceil(self.columns);
+    }
+
+    // rows of habits that fit above the status and message lines
+    pub fn rows_shown(&self) -> usize {
+        return (self.size.y.saturating_sub(2) / VIEW_HEIGHT).max(1);
+    }
+
+    // scroll the grid just enough to bring the focused habit into
+    // view, without leaving empty rows below the last habit
+    pub fn scroll_to_focus(&mut self) {
+        let (row, shown) = (self.focus / self.columns, self.rows_shown());
+        if row < self.scroll {
+            self.scroll = row;
+        } else if row >= self.scroll + shown {
+            self.scroll = row + 1 - shown;
+        }
+        self.scroll = self.scroll.min(self.rows().saturating_sub(shown));
+    }
+
+    // read the habits saved in `file`, a missing file holds no habits
+    pub fn read_habits(file: &Path) -> serde_json::Result<Vec<Box<dyn HabitWrapper>>> {
+        if let Ok(ref mut f) = File::open(file) {
+            let mut j = String::new();
+            f.read_to_string(&mut j);
+            return serde_json::from_str(&j);
+        } else {
+            return Ok(Vec::new());
+        }
+    }
+
+    // read the habits of `file`, reporting a recovery on the
+    // message line
+    fn recover(&mut self, file: &Path) -> Vec<Box<dyn HabitWrapper>> {
+        let (habits, error) = match recover_habits(file) {
+            Ok(recovered) => recovered,
+            Err(e) => {
+                // the corrupt file is the only copy of its habits
+                self.corrupt.push(file.to_owned());
+                (Vec::new(), Some(e))
             }
         };
-        Vec2::new(width, height + 2)
+        if let Some(e) = error {
+            // write the recovered habits back on the next save
+            if !self.corrupt.iter().any(|f| f == file) {
+                self.mark_modified(file == utils::auto_habit_file());
+            }
+            self.message.set_kind(MessageKind::Error);
+            self.message.set_message(e);
+        }
+        return habits;
     }
 
-    pub fn load_state() -> Self {
-        let (regular_f, auto_f) = (utils::habit_file(), utils::auto_habit_file());
-        let read_from_file = |file: PathBuf| -> Vec<Box<dyn HabitWrapper>> {
-            if let Ok(ref mut f) = File::open(file) {
-                let mut j = String::new();
-                f.read_to_string(&mut j);
-                return serde_json::from_str(&j).unwrap();
-            } else {
-                return Vec::new();
+    // the contents of a data file when it was last loaded or saved
+    fn on_disk_mut(&mut self, auto: bool) -> &mut String {
+        if auto {
+            return &mut self.auto_on_disk;
+        } else {
+            return &mut self.regular_on_disk;
+        }
+    }
+
+    // replace the habits of a data file, keeping the cursor and view
+    // mode of every habit that is still there
+    pub fn replace_habits(&mut self, mut habits: Vec<Box<dyn HabitWrapper>>, auto: bool) {
+        for h in habits.iter_mut() {
+            if let Some(old) = self.habits.iter().find(|o| o.name() == h.name()) {
+                let (cursor, mode) = (old.inner_data_ref().cursor, old.inner_data_ref().view_mode);
+                h.inner_data_mut_ref().cursor = cursor;
+                h.inner_data_mut_ref().view_mode = mode;
             }
+        }
+        // regular habits are listed before auto habits
+        let kept = self.habits.drain(..).filter(|h| h.is_auto() != auto);
+        self.habits = if auto {
+            kept.chain(habits).collect()
+        } else {
+            habits.into_iter().chain(kept).collect()
         };
+        self.focus = self.focus.min(self.visible_count().saturating_sub(1));
+    }
+
+    // replace the habits of `file` with its contents on disk, unless
+    // it is unchanged since it was last loaded or saved
+    pub fn reload(&mut self, file: &Path, auto: bool) {
+        let read = utils::DataLock::shared().and_then(|_lock| {
+            let j = fs::read_to_string(file)?;
+            let habits = serde_json::from_str(&j)?;
+            Ok((j, habits))
+        });
+        match read {
+            Ok((j, habits)) => {
+                if j != *self.on_disk_mut(auto) {
+                    self.replace_habits(habits, auto);
+                    *self.on_disk_mut(auto) = j;
+                }
+            }
+            Err(e) => {
+                self.message.set_kind(MessageKind::Error);
+                self.message
+                    .set_message(format!("Unable to reload `{}`: {}", file.display(), e));
+            }
+        }
+    }
 
-        let mut regular = read_from_file(regular_f);
-        let auto = read_from_file(auto_f);
+    pub fn load_state() -> Self {
+        let (regular_f, auto_f) = (utils::habit_file(), utils::auto_habit_file());
+        let mut app = App::new();
+        let mut regular = app.recover(&regular_f);
+        let auto = app.recover(&auto_f);
         regular.extend(auto);
-        return App {
-            habits: regular,
-            ..Default::default()
-        };
+        app.habits = regular;
+        app.regular_on_disk = fs::read_to_string(&regular_f).unwrap_or_default();
+        app.auto_on_disk = fs::read_to_string(&auto_f).unwrap_or_default();
+        return app;
     }
 
-    // this function does IO
+    // this function does IO, callers should hold an exclusive `DataLock`
     // TODO: convert this into non-blocking async function
-    pub fn save_state(&self) {
-        let (regular, auto): (Vec<_>, Vec<_>) = self.habits.iter().partition(|&x| !x.is_auto());
-        let (regular_f, auto_f) = (utils::habit_file(), utils::auto_habit_file());
+    pub fn save_state(&mut self) -> io::Result<()> {
+        // a file that cannot be written does not keep the other
+        // one from being saved
+        let mut saved = Ok(());
+        if self.regular_modified {
+            match self.save_file(false) {
+                Ok(()) => self.regular_modified = false,
+                Err(e) => saved = Err(e),
+            }
+        }
+        if self.auto_modified {
+            match self.save_file(true) {
+                Ok(()) => self.auto_modified = false,
+                Err(e) => saved = saved.and(Err(e)),
+            }
+        }
+        return saved;
+    }
 
-        let write_to_file = |data: Vec<&Box<dyn HabitWrapper>>, file: PathBuf| {
-            let j = serde_json::to_string_pretty(&data).unwrap();
-            match OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(file)
-            {
-                Ok(ref mut f) => f.write_all(j.as_bytes()).unwrap(),
-                Err(_) => panic!("Unable to write!"),
-            };
+    // write the habits of a data file. Changes another dijo process
+    // made to the file since it was last loaded or saved are merged
+    // in first, so they are never lost.
+    fn save_file(&mut self, auto: bool) -> io::Result<()> {
+        let file = if auto {
+            utils::auto_habit_file()
+        } else {
+            utils::habit_file()
         };
-
-        write_to_file(regular, regular_f);
-        write_to_file(auto, auto_f);
+        if self.corrupt.contains(&file) {
+            return Err(io::Error::other(format!(
+                "`{}` is corrupt and was not overwritten",
+                file.display()
+            )));
+        }
+        let on_disk = fs::read_to_string(&file).unwrap_or_default();
+        let base = self.on_disk_mut(auto).clone();
+        if on_disk != base {
+            self.merge_from_disk(&base, &on_disk, auto);
+        }
+        let habits = self
+            .habits
+            .iter()
+            .filter(|h| h.is_auto() == auto)
+            .collect::<Vec<_>>();
+        let j = serde_json::to_string_pretty(&habits).unwrap();
+        utils::write_atomically(&file, j.as_bytes())?;
+        *self.on_disk_mut(auto) = j;
+        Ok(())
     }
 
     pub fn parse_command(&mut self, result: Result<Command, CommandLineError>) {
-        let mut _track = |name: &str, event: TrackEvent| {
+        let mut _track = |name: &str, date: NaiveDate, event: TrackEvent, times: u32| {
             let target_habit = self
                 .habits
                 .iter_mut()
                 .find(|x| x.name() == name && x.is_auto());
             if let Some(h) = target_habit {
-                h.modify(Local::now().naive_local().date(), event);
+                let before = h.value(date);
+                for _ in 0..times {
+                    h.modify(date, event);
+                }
+                // nothing to save or undo if the value is unchanged
+                if h.value(date) != before {
+                    self.auto_modified = true;
+                    self.history
+                        .record(vec![Change::Entry(name.into(), date, before)]);
+                }
+                return true;
             }
+            return false;
         };
         match result {
             Ok(c) => match c {
@@ -227,33 +857,164 @@ impl App {
                             .set_message(format!("Habit `{}` already exist", &name));
                         return;
                     }
-                    match goal {
-                        Some(GoalKind::Bit) => {
-                            self.add_habit(Box::new(Bit::new(name, auto)));
-                        }
-                        Some(GoalKind::Count(v)) => {
-                            self.add_habit(Box::new(Count::new(name, v, auto)));
+                    self.add_habit(new_habit(&name, goal, auto));
+                    self.mark_modified(auto);
+                    self.record(vec![Change::Remove(name.clone())]);
+                    self.message.set_kind(MessageKind::Info);
+                    self.message.set_message(format!("Added habit `{}`", name));
+                }
+                Command::Delete(name) => {
+                    if self.delete_by_name(&name) {
+                        self.message.set_kind(MessageKind::Info);
+                        self.message
+                            .set_message(format!("Deleted habit `{}`", name));
+                    }
+                    self.focus = 0;
+                }
+                Command::Archive(name) => {
+                    if self.set_archived(&name, true) {
+                        self.message.set_kind(MessageKind::Info);
+                        self.message
+                            .set_message(format!("Archived habit `{}`", name));
+                    }
+                }
+                Command::Unarchive(name) => {
+                    if self.set_archived(&name, false) {
+                        self.message.set_kind(MessageKind::Info);
+                        self.message
+                            .set_message(format!("Unarchived habit `{}`", name));
+                    }
+                }
+                Command::Rename(old, new) => {
+                    if self.rename_habit(&old, &new) {
+                        self.message.set_kind(MessageKind::Info);
+                        self.message
+                            .set_message(format!("Renamed habit `{}` to `{}`", old, new));
+                    }
+                }
+                Command::Goal(name, goal) => {
+                    if self.set_goal(&name, goal) {
+                        let goal = self
+                            .habits
+                            .iter()
+                            .find(|h| h.name() == name)
+                            .unwrap()
+                            .goal_expr();
+                        self.message.set_kind(MessageKind::Info);
+                        self.message
+                            .set_message(format!("Set goal of `{}` to {}", name, goal));
+                    }
+                }
+                Command::Schedule(name, schedule) => {
+                    let message = format!("Set schedule of `{}` to {}", name, schedule);
+                    if self.set_schedule(&name, schedule) {
+                        self.message.set_kind(MessageKind::Info);
+                        self.message.set_message(message);
+                    }
+                }
+                Command::Excuse(name, date, excused) => {
+                    let date = date.unwrap_or(self.cursor.0);
+                    let found = match name.as_ref() {
+                        Some(n) => self.habits.iter().any(|h| &h.name() == n),
+                        None => true,
+                    };
+                    self.set_excused(name.as_deref(), date, excused);
+                    if found {
+                        self.excuse_message(name.as_deref(), date, excused);
+                    }
+                }
+                Command::Note(name, date, note) => {
+                    let date = date.unwrap_or(self.cursor.0);
+                    self.set_note(&name, date, &note);
+                }
+                Command::TrackUp(name, date, by) => {
+                    if _track(&name, date, TrackEvent::Increment, by) {
+                        self.message.set_kind(MessageKind::Info);
+                        self.message
+                            .set_message(format!("Tracked `{}` up on {}", name, date));
+                    } else {
+                        self.message.set_kind(MessageKind::Error);
+                        self.message
+                            .set_message(format!("No auto habit named `{}`", name));
+                    }
+                }
+                Command::TrackDown(name, date, by) => {
+                    if _track(&name, date, TrackEvent::Decrement, by) {
+                        self.message.set_kind(MessageKind::Info);
+                        self.message
+                            .set_message(format!("Tracked `{}` down on {}", name, date));
+                    } else {
+                        self.message.set_kind(MessageKind::Error);
+                        self.message
+                            .set_message(format!("No auto habit named `{}`", name));
+                    }
+                }
+                Command::TrackSet(name, date, value) => {
+                    let target_habit = self
+                        .habits
+                        .iter_mut()
+                        .find(|x| x.name() == name && x.is_auto());
+                    let before = target_habit.as_ref().and_then(|h| h.value(date));
+                    match target_habit.map(|h| (h.set_value(date, &value), h.value(date))) {
+                        Some((true, after)) => {
+                            if after != before {
+                                self.auto_modified = true;
+                                self.record(vec![Change::Entry(name.clone(), date, before)]);
+                            }
+                            self.message.set_kind(MessageKind::Info);
+                            self.message
+                                .set_message(format!("Set `{}` to {} on {}", name, value, date));
                         }
-                        Some(GoalKind::Float(v, p)) => {
+                        Some((false, _)) => {
                             self.message.set_kind(MessageKind::Error);
-                            self.message.set_message(format!("Added floating habit"));
-                            self.add_habit(Box::new(Float::new(name, v, p, auto)));
+                            self.message.set_message(format!(
+                                "Invalid value `{}` for habit `{}`",
+                                value, name
+                            ));
                         }
-                        _ => {
-                            self.add_habit(Box::new(Count::new(name, 0, auto)));
+                        None => {
+                            self.message.set_kind(MessageKind::Error);
+                            self.message
+                                .set_message(format!("No auto habit named `{}`", name));
                         }
                     }
                 }
-                Command::Delete(name) => {
-                    self.delete_by_name(&name);
-                    self.focus = 0;
-                }
-                Command::TrackUp(name) => {
-                    _track(&name, TrackEvent::Increment);
-                }
-                Command::TrackDown(name) => {
-                    _track(&name, TrackEvent::Decrement);
+                Command::Export(format, path) => match File::create(&path)
+                    .and_then(|f| self.export(format, &mut io::BufWriter::new(f)))
+                {
+                    Ok(n) => {
+                        self.message.set_kind(MessageKind::Info);
+                        self.message
+                            .set_message(format!("Exported {} entries to `{}`", n, path));
+                    }
+                    Err(e) => {
+                        self.message.set_kind(MessageKind::Error);
+                        self.message
+                            .set_message(format!("Unable to export to `{}`: {}", path, e));
+                    }
+                },
+                Command::Import(format, path) => {
+                    let imported = File::open(&path)
+                        .map_err(ImportError::from)
+                        .and_then(|mut f| import::read_records(format, &mut f))
+                        .and_then(|records| self.import(records));
+                    match imported {
+                        Ok(report) => {
+                            self.message.set_kind(MessageKind::Info);
+                            self.message.set_message(match report.conflicts.first() {
+                                Some(c) => format!("{}, first: {}", report, c),
+                                None => report.to_string(),
+                            });
+                        }
+                        Err(e) => {
+                            self.message.set_kind(MessageKind::Error);
+                            self.message
+                                .set_message(format!("Unable to import `{}`: {}", path, e));
+                        }
+                    }
                 }
+                Command::Undo => self.undo(),
+                Command::Redo => self.redo(),
                 Command::Help(input) => {
                     if let Some(topic) = input.as_ref().map(String::as_ref) {
                         self.message.set_message(
@@ -261,14 +1022,31 @@ impl App {
                                 "a"     | "add" => "add <habit-name> [goal]     (alias: a)",
                                 "aa"    | "add-auto" => "add-auto <habit-name> [goal]     (alias: aa)",
                                 "d"     | "delete" => "delete <habit-name>     (alias: d)",
+                                "ar"    | "archive" => "archive <habit-name>     (alias: ar)",
+                                "unar"  | "unarchive" => "unarchive <habit-name>     (alias: unar)",
+                                "rn"    | "rename" => "rename <habit-name> <new-name>     (alias: rn)",
+                                "g"     | "goal" => "goal <habit-name> <goal>     (alias: g)",
+                                "ex"    | "excuse" => "excuse [<habit-name>] [--date YYYY-MM-DD], every habit if none is given     (alias: ex)",
+                                "unex"  | "unexcuse" => "unexcuse [<habit-name>] [--date YYYY-MM-DD]     (alias: unex)",
+                                "st"    | "stats" => "stats <habit-name>, q closes the view     (alias: st)",
+                                "nt"    | "note" => "note <habit-name> [--date YYYY-MM-DD] [<text>], no text clears the note     (alias: nt)",
+                                "sc"    | "schedule" => "schedule <habit-name> <daily|weekdays|weekends|mon,wed,fri|mon-fri|every N days>     (alias: sc)",
                                 "mprev" | "month-prev" => "month-prev     (alias: mprev)",
                                 "mnext" | "month-next" => "month-next     (alias: mnext)",
-                                "tup"   | "track-up" => "track-up <auto-habit-name>     (alias: tup)",
-                                "tdown" | "track-down" => "track-down <auto-habit-name>     (alias: tdown)",
+                                "yprev" | "year-prev" => "year-prev     (alias: yprev)",
+                                "ynext" | "year-next" => "year-next     (alias: ynext)",
+                                "gt"    | "goto" => "goto <date>, YYYY-MM-DD, today, yesterday, -3d, -2w, -1m, -1y, monday or last monday     (alias: gt)",
+                                "tup"   | "track-up" => "track-up <auto-habit-name> [--date YYYY-MM-DD] [--by N]     (alias: tup)",
+                                "tdown" | "track-down" => "track-down <auto-habit-name> [--date YYYY-MM-DD] [--by N]     (alias: tdown)",
+                                "e"     | "export" => "export <csv|jsonl|markdown> <path>     (alias: e)",
+                                "i"     | "import" => "import <csv|loop> <path>     (alias: i)",
+                                "tset"  | "track-set" => "track-set <auto-habit-name> <value> [--date YYYY-MM-DD]     (alias: tset)",
                                 "q"     | "quit" => "quit dijo",
+                                "u"     | "undo" => "undo the last change     (alias: u)",
+                                "redo" => "redo the last undone change",
                                 "w"     | "write" => "write current state to disk   (alias: w)",
                                 "h"|"?" | "help" => "help [<command>|commands|keys]     (aliases: h, ?)",
-                                "cmds"  | "commands" => "add, add-auto, delete, month-{prev,next}, track-{up,down}, help, quit",
+                                "cmds"  | "commands" => "add, add-auto, delete, archive, unarchive, rename, goal, schedule, excuse, unexcuse, note, month-{prev,next}, year-{prev,next}, goto, stats, track-{up,down,set}, export, import, undo, redo, help, quit",
                                 "keys" => "TODO", // TODO (view?)
                                 "wq" =>   "write current state to disk and quit dijo",
                                 _ => "unknown command or help topic.",
@@ -279,9 +1057,28 @@ impl App {
                         self.message.set_message("help <command>|commands|keys")
                     }
                 }
-                Command::Quit | Command::Write | Command::WriteAndQuit => self.save_state(),
+                Command::Quit | Command::Write | Command::WriteAndQuit => {
+                    // these are interactive only, no lock is held yet
+                    let saved = utils::DataLock::exclusive().and_then(|_lock| self.save_state());
+                    if let Err(e) = saved {
+                        self.message.set_kind(MessageKind::Error);
+                        self.message
+                            .set_message(format!("Unable to save habits: {}", e));
+                    }
+                }
                 Command::MonthNext => self.sift_forward(),
                 Command::MonthPrev => self.sift_backward(),
+                Command::YearNext => self.sift_year_forward(),
+                Command::YearPrev => self.sift_year_backward(),
+                Command::Goto(date) => self.goto(date),
+                Command::Stats(name) => {
+                    // the view itself is opened by the command window
+                    if !self.habits.iter().any(|h| h.name() == name) {
+                        self.message.set_kind(MessageKind::Error);
+                        self.message
+                            .set_message(format!("Could not find habit `{}`", name));
+                    }
+                }
                 Command::Blank => {}
             },
             Err(e) => {
@@ -291,3 +1088,75 @@ impl App {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::process;
+
+    // a data file in an empty directory, with backups of the given
+    // contents, oldest first
+    fn data_file(test: &str, contents: &str, backups: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dijo-{}-{}", test, process::id()));
+        fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("backups")).unwrap();
+        let file = dir.join("habit_record.json");
+        fs::write(&file, contents).unwrap();
+        for (i, b) in backups.iter().enumerate() {
+            let name = format!("habit_record.json.20200101T00000{}.000", i);
+            fs::write(dir.join("backups").join(name), b).unwrap();
+        }
+        return file;
+    }
+
+    fn habits_json(name: &str) -> String {
+        let habits: Vec<Box<dyn HabitWrapper>> = vec![Box::new(Count::new(name, 3, false))];
+        return serde_json::to_string(&habits).unwrap();
+    }
+
+    #[test]
+    fn readable_file_is_not_recovered() {
+        let file = data_file("recover-ok", &habits_json("walk"), &[]);
+        let (habits, error) = recover_habits(&file).unwrap();
+        assert_eq!(habits[0].name(), "walk");
+        assert!(error.is_none());
+        fs::remove_dir_all(file.parent().unwrap());
+    }
+
+    #[test]
+    fn corrupt_file_is_restored_from_newest_readable_backup() {
+        let (older, newer) = (habits_json("walk"), habits_json("run"));
+        let file = data_file("recover", "{", &[&older, &newer, "["]);
+        let (habits, error) = recover_habits(&file).unwrap();
+        assert_eq!(habits.len(), 1);
+        assert_eq!(habits[0].name(), "run");
+        assert!(error.unwrap().contains("restored from backup"));
+
+        let mut corrupt = file.as_os_str().to_owned();
+        corrupt.push(".corrupt");
+        assert_eq!(fs::read_to_string(&corrupt).unwrap(), "{");
+        fs::remove_dir_all(file.parent().unwrap());
+    }
+
+    #[test]
+    fn corrupt_file_without_backups_is_kept() {
+        let file = data_file("recover-none", "{", &["["]);
+        let (habits, error) = recover_habits(&file).unwrap();
+        assert!(habits.is_empty());
+        assert!(error.unwrap().contains("no backup could be read"));
+        fs::remove_dir_all(file.parent().unwrap());
+    }
+
+    #[test]
+    fn corrupt_file_that_cannot_be_kept_is_not_recovered() {
+        let file = data_file("recover-keep", "{", &[&habits_json("walk")]);
+        let mut corrupt = file.as_os_str().to_owned();
+        corrupt.push(".corrupt");
+        // copying onto a directory fails
+        fs::create_dir(&corrupt).unwrap();
+        assert!(recover_habits(&file).is_err());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "{");
+        fs::remove_dir_all(file.parent().unwrap());
+    }
+}
@@ -1,8 +1,3 @@
-use std::f64;
-use std::fs::File;
-use std::io::prelude::*;
-use std::path::PathBuf;
-
 use cursive::direction::{Absolute, Direction};
 use cursive::event::{Event, EventResult, Key};
 use cursive::theme::Color;
@@ -10,47 +5,56 @@ use cursive::view::{CannotFocus, View};
 use cursive::{Printer, Vec2};
 use notify::DebouncedEvent;
 
-use crate::app::{App, MessageKind};
-use crate::habit::{HabitWrapper, ViewMode};
-use crate::utils::{self, GRID_WIDTH, VIEW_HEIGHT, VIEW_WIDTH};
+use crate::app::{App, Change, MessageKind};
+use crate::habit::ViewMode;
+use crate::keybinds::Action;
+use crate::note;
+use crate::stats;
+use crate::utils::{self, VIEW_HEIGHT, VIEW_WIDTH};
+use crate::CONFIGURATION;
 
 impl View for App {
     fn draw(&self, printer: &Printer) {
-        let mut offset = Vec2::zero();
-        for (idx, habit) in self.habits.iter().enumerate() {
-            if idx >= GRID_WIDTH && idx % GRID_WIDTH == 0 {
-                offset = offset.map_y(|y| y + VIEW_HEIGHT).map_x(|_| 0);
+        let shown = self.scroll..self.scroll + self.rows_shown();
+        for (idx, habit) in self.visible().enumerate() {
+            let (row, col) = (idx / self.columns(), idx % self.columns());
+            if !shown.contains(&row) {
+                continue;
             }
+            let offset = Vec2::new(col * (VIEW_WIDTH + 2), (row - self.scroll) * VIEW_HEIGHT);
             habit.draw(&printer.offset(offset).focused(self.focus == idx));
-            offset = offset.map_x(|x| x + VIEW_WIDTH + 2);
         }
 
-        offset = offset.map_x(|_| 0).map_y(|_| self.max_size().y - 2);
+        // status and message lines stay at the bottom, below
+        // however many rows of habits fit
+        let mut offset = Vec2::new(0, self.size.y.saturating_sub(2));
 
         let status = self.status();
         printer.print(offset, &status.0); // left status
 
-        let full = self.max_size().x;
-        offset = offset.map_x(|_| full - status.1.len());
+        let full = self.size.x;
+        offset = offset.map_x(|_| full.saturating_sub(status.1.len()));
         printer.print(offset, &status.1); // right status
 
-        offset = offset.map_x(|_| 0).map_y(|_| self.max_size().y - 1);
+        offset = offset.map_x(|_| 0).map_y(|_| self.size.y.saturating_sub(1));
         printer.with_style(Color::from(self.message.kind()), |p| {
             p.print(offset, self.message.contents())
         });
     }
 
-    fn required_size(&mut self, _: Vec2) -> Vec2 {
-        let width = GRID_WIDTH * (VIEW_WIDTH + 2);
-        let height = {
-            if self.habits.len() > 0 {
-                (VIEW_HEIGHT as f64 * (self.habits.len() as f64 / GRID_WIDTH as f64).ceil())
-                    as usize
-            } else {
-                0
-            }
-        };
-        Vec2::new(width, height + 2)
+    fn layout(&mut self, size: Vec2) {
+        self.size = size;
+        self.set_columns(CONFIGURATION.grid_columns(size.x));
+        self.scroll_to_focus();
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        // as many columns as fit in the terminal
+        self.set_columns(CONFIGURATION.grid_columns(constraint.x));
+        let width = self.grid_width();
+        let height = VIEW_HEIGHT * self.rows() + 2;
+        // taller grids scroll within the available height
+        Vec2::new(width, height.min(constraint.y))
     }
 
     fn take_focus(&mut self, _: Direction) -> Result<EventResult, CannotFocus> {
@@ -58,102 +62,154 @@ impl View for App {
     }
 
     fn on_event(&mut self, e: Event) -> EventResult {
-        match self.file_event_recv.try_recv() {
-            Ok(DebouncedEvent::Write(_)) => {
-                let read_from_file = |file: PathBuf| -> Vec<Box<dyn HabitWrapper>> {
-                    if let Ok(ref mut f) = File::open(file) {
-                        let mut j = String::new();
-                        f.read_to_string(&mut j);
-                        return serde_json::from_str(&j).unwrap();
-                    } else {
-                        return Vec::new();
-                    }
-                };
-                let auto = read_from_file(utils::auto_habit_file());
-                self.habits.retain(|x| !x.is_auto());
-                self.habits.extend(auto);
-            }
-            _ => {}
-        };
-        if self.habits.is_empty() {
-            return EventResult::Ignored;
+        let (regular_f, auto_f) = (utils::habit_file(), utils::auto_habit_file());
+        let (mut regular_changed, mut auto_changed) = (false, false);
+        for event in self.file_event_recv.try_iter() {
+            let path = match event {
+                DebouncedEvent::Create(p) | DebouncedEvent::Write(p) => p,
+                DebouncedEvent::Rename(_, p) => p,
+                _ => continue,
+            };
+            regular_changed |= path == regular_f;
+            auto_changed |= path == auto_f;
+        }
+        // files with unsaved changes are merged with the file on
+        // disk on the next save instead
+        if regular_changed && !self.regular_modified {
+            self.reload(&regular_f, false);
+        }
+        if auto_changed && !self.auto_modified {
+            self.reload(&auto_f, true);
         }
-        match e {
-            Event::Key(Key::Right) | Event::Key(Key::Tab) | Event::Char('l') => {
+        if e == Event::Key(Key::Esc) {
+            self.set_global_mode(ViewMode::Day);
+            self.reset_cursor();
+            return EventResult::Consumed(None);
+        }
+
+        // arrow keys and tabs always move focus, every other
+        // key goes through the configured keybinds
+        let action = match e {
+            Event::Key(Key::Right) | Event::Key(Key::Tab) => Some(Action::FocusRight),
+            Event::Key(Key::Left) | Event::Shift(Key::Tab) => Some(Action::FocusLeft),
+            Event::Key(Key::Up) => Some(Action::FocusUp),
+            Event::Key(Key::Down) => Some(Action::FocusDown),
+            _ => CONFIGURATION.keybinds.action(&e),
+        };
+        match action {
+            Some(Action::FocusRight) => {
                 self.set_focus(Absolute::Right);
                 return EventResult::Consumed(None);
             }
-            Event::Key(Key::Left) | Event::Shift(Key::Tab) | Event::Char('h') => {
+            Some(Action::FocusLeft) => {
                 self.set_focus(Absolute::Left);
                 return EventResult::Consumed(None);
             }
-            Event::Key(Key::Up) | Event::Char('k') => {
+            Some(Action::FocusUp) => {
                 self.set_focus(Absolute::Up);
                 return EventResult::Consumed(None);
             }
-            Event::Key(Key::Down) | Event::Char('j') => {
+            Some(Action::FocusDown) => {
                 self.set_focus(Absolute::Down);
                 return EventResult::Consumed(None);
             }
 
-            Event::Char('K') => {
+            Some(Action::CursorUp) => {
                 self.move_cursor(Absolute::Up);
                 return EventResult::Consumed(None);
             }
-            Event::Char('H') => {
+            Some(Action::CursorLeft) => {
                 self.move_cursor(Absolute::Left);
                 return EventResult::Consumed(None);
             }
-            Event::Char('J') => {
+            Some(Action::CursorDown) => {
                 self.move_cursor(Absolute::Down);
                 return EventResult::Consumed(None);
             }
-            Event::Char('L') => {
+            Some(Action::CursorRight) => {
                 self.move_cursor(Absolute::Right);
                 return EventResult::Consumed(None);
             }
 
-            Event::Char('v') => {
-                if self.habits.is_empty() {
-                    return EventResult::Consumed(None);
-                }
-                if self.habits[self.focus].inner_data_ref().view_mode() == ViewMode::Week {
-                    self.set_mode(ViewMode::Day)
-                } else {
-                    self.set_mode(ViewMode::Week)
-                }
+            Some(Action::WeekMode) => {
+                self.toggle_mode(ViewMode::Week);
                 return EventResult::Consumed(None);
             }
-            Event::Char('V') => {
-                for habit in self.habits.iter_mut() {
-                    habit.inner_data_mut_ref().set_view_mode(ViewMode::Week);
-                }
+            Some(Action::GlobalWeekMode) => {
+                self.set_global_mode(ViewMode::Week);
                 return EventResult::Consumed(None);
             }
-            Event::Key(Key::Esc) => {
-                for habit in self.habits.iter_mut() {
-                    habit.inner_data_mut_ref().set_view_mode(ViewMode::Day);
-                }
-                self.reset_cursor();
+            Some(Action::MonthMode) => {
+                self.toggle_mode(ViewMode::Month);
+                return EventResult::Consumed(None);
+            }
+            Some(Action::GlobalMonthMode) => {
+                self.set_global_mode(ViewMode::Month);
+                return EventResult::Consumed(None);
+            }
+            Some(Action::YearMode) => {
+                self.toggle_mode(ViewMode::Year);
+                return EventResult::Consumed(None);
+            }
+            Some(Action::GlobalYearMode) => {
+                self.set_global_mode(ViewMode::Year);
                 return EventResult::Consumed(None);
             }
 
             /* We want sifting to be an app level function,
              * that later trickles down into each habit
              * */
-            Event::Char(']') => {
+            Some(Action::MonthNext) => {
                 self.sift_forward();
                 return EventResult::Consumed(None);
             }
-            Event::Char('[') => {
+            Some(Action::MonthPrev) => {
                 self.sift_backward();
                 return EventResult::Consumed(None);
             }
-            Event::Char('}') => {
+            Some(Action::YearNext) => {
+                self.sift_year_forward();
+                return EventResult::Consumed(None);
+            }
+            Some(Action::YearPrev) => {
+                self.sift_year_backward();
+                return EventResult::Consumed(None);
+            }
+            Some(Action::ResetCursor) => {
                 self.reset_cursor();
                 return EventResult::Consumed(None);
             }
-            Event::CtrlChar('l') => {
+            Some(Action::Undo) => {
+                self.undo();
+                self.save_changes();
+                return EventResult::Consumed(None);
+            }
+            Some(Action::Redo) => {
+                self.redo();
+                self.save_changes();
+                return EventResult::Consumed(None);
+            }
+            Some(Action::Excuse) => {
+                self.toggle_excused(false);
+                self.save_changes();
+                return EventResult::Consumed(None);
+            }
+            Some(Action::GlobalExcuse) => {
+                self.toggle_excused(true);
+                self.save_changes();
+                return EventResult::Consumed(None);
+            }
+            Some(Action::Note) => {
+                return EventResult::with_cb(note::open_note_window);
+            }
+            Some(Action::Stats) => {
+                let name = match self.visible().nth(self.focus) {
+                    Some(h) => h.name(),
+                    None => return EventResult::Consumed(None),
+                };
+                return EventResult::with_cb(move |s| stats::open_stats_window(s, &name));
+            }
+            Some(Action::ClearMessage) => {
                 self.message.clear();
                 self.message.set_kind(MessageKind::Info);
                 return EventResult::Consumed(None);
@@ -163,10 +219,25 @@ impl View for App {
              * down to the focused habit.
              * */
             _ => {
-                if self.habits.is_empty() {
-                    return EventResult::Ignored;
+                let habit = match self.focused_mut() {
+                    Some(h) => h,
+                    None => return EventResult::Ignored,
+                };
+                let date = habit.inner_data_ref().cursor().0;
+                let before = habit.value(date);
+                let result = habit.on_event(e);
+                // nothing to save or undo if the value is unchanged,
+                // e.g. clearing a day that was never tracked
+                if let EventResult::Consumed(_) = result {
+                    if habit.value(date) == before {
+                        return result;
+                    }
+                    let name = habit.name();
+                    self.mark_modified(false);
+                    self.record(vec![Change::Entry(name, date, before)]);
+                    self.save_changes();
                 }
-                self.habits[self.focus].on_event(e)
+                result
             }
         }
     }
@@ -0,0 +1,177 @@
+use serde_json::{Map, Value};
+
+use crate::app::App;
+use crate::habit::HabitWrapper;
+
+// Where a value sits within a habit
+#[derive(Clone, Copy, PartialEq)]
+enum Level {
+    Other,
+    Stats, // the tracked values of a habit
+    Entry, // a single tracked value, or the `value` of a float
+}
+
+// Three way merge of JSON values: `ours` and `theirs` both started
+// out as `base`, a change made on one side only is kept. Objects
+// changed on both sides are merged key by key. Both sides may have
+// tracked the same day, numbers changed on both sides in a tracked
+// value get the changes of both added up. Any other value changed
+// on both sides keeps our version. `None` is a missing value.
+fn merge_values(
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    level: Level,
+) -> Option<Value> {
+    if ours == base {
+        return theirs.cloned();
+    }
+    if theirs == base {
+        return ours.cloned();
+    }
+    let number = |v: Option<&Value>| v.map_or(Some(0), Value::as_u64);
+    if let (Level::Entry, Some(b), Some(o), Some(t)) =
+        (level, number(base), number(ours), number(theirs))
+    {
+        if ours.is_some() && theirs.is_some() {
+            return Some((t + o).saturating_sub(b).into());
+        }
+    }
+    let empty = Map::new();
+    let base = match base {
+        Some(Value::Object(b)) => Some(b),
+        None => Some(&empty),
+        _ => None,
+    };
+    match (base, ours, theirs) {
+        (Some(b), Some(Value::Object(o)), Some(Value::Object(t))) => {
+            let mut merged = Map::new();
+            let keys = o.keys().chain(t.keys().filter(|k| !o.contains_key(*k)));
+            for key in keys {
+                let level = match (level, key.as_str()) {
+                    (Level::Other, "stats") => Level::Stats,
+                    (Level::Stats, _) | (Level::Entry, "value") => Level::Entry,
+                    _ => Level::Other,
+                };
+                if let Some(v) = merge_values(b.get(key), o.get(key), t.get(key), level) {
+                    merged.insert(key.clone(), v);
+                }
+            }
+            return Some(Value::Object(merged));
+        }
+        _ => return ours.cloned(),
+    }
+}
+
+// Merge the habits of a data file by name, see `merge_values`. Our
+// habits keep their order, habits only found in `theirs` follow.
+fn merge_habits(base: &[Value], ours: &[Value], theirs: &[Value]) -> Vec<Value> {
+    let only_theirs = theirs.iter().filter(|t| find(ours, &t["name"]).is_none());
+    return ours
+        .iter()
+        .chain(only_theirs)
+        .filter_map(|v| {
+            let name = &v["name"];
+            merge_values(
+                find(base, name),
+                find(ours, name),
+                find(theirs, name),
+                Level::Other,
+            )
+        })
+        .collect();
+}
+
+fn find<'a>(habits: &'a [Value], name: &Value) -> Option<&'a Value> {
+    return habits.iter().find(|h| h["name"] == *name);
+}
+
+impl App {
+    // Another dijo process changed a data file from `base`, its
+    // contents when last loaded or saved, to `on_disk`. Take those
+    // changes over into the habits of the file, on top of ours.
+    pub fn merge_from_disk(&mut self, base: &str, on_disk: &str, auto: bool) {
+        let parse = |j: &str| serde_json::from_str::<Vec<Value>>(j).ok();
+        // an unreadable file is replaced by our habits
+        let theirs = match parse(on_disk) {
+            Some(t) => t,
+            None => return,
+        };
+        let base = parse(base).unwrap_or_default();
+        let ours = self
+            .habits
+            .iter()
+            .filter(|h| h.is_auto() == auto)
+            .filter_map(|h| serde_json::to_value(h).ok())
+            .collect::<Vec<_>>();
+        let merged = Value::Array(merge_habits(&base, &ours, &theirs));
+        if let Ok(habits) = serde_json::from_value::<Vec<Box<dyn HabitWrapper>>>(merged) {
+            self.replace_habits(habits, auto);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn changes_on_both_sides_are_kept() {
+        let walk = json!({"name": "walk", "goal": 2, "stats": {}});
+        let base = vec![
+            walk.clone(),
+            json!({"name": "read", "goal": 1, "stats": {}}),
+        ];
+        // we tracked walk and deleted read, they tracked walk on
+        // another day and added run
+        let ours = vec![json!({"name": "walk", "goal": 2, "stats": {"2021-03-01": 2}})];
+        let theirs = vec![
+            json!({"name": "walk", "goal": 2, "stats": {"2021-03-02": 1}}),
+            base[1].clone(),
+            json!({"name": "run", "goal": 1, "stats": {}}),
+        ];
+        let merged = merge_habits(&base, &ours, &theirs);
+        assert_eq!(
+            merged,
+            vec![
+                json!({"name": "walk", "goal": 2, "stats": {"2021-03-01": 2, "2021-03-02": 1}}),
+                json!({"name": "run", "goal": 1, "stats": {}}),
+            ]
+        );
+    }
+
+    #[test]
+    fn tracking_the_same_day_adds_up() {
+        let base = vec![
+            json!({"name": "walk", "stats": {"2021-03-01": 1}}),
+            json!({"name": "run", "stats": {}}),
+        ];
+        let ours = vec![
+            json!({"name": "walk", "stats": {"2021-03-01": 3, "2021-03-02": 1}}),
+            json!({"name": "run", "stats": {"2021-03-01": {"value": 15, "precision": 1}}}),
+        ];
+        let theirs = vec![
+            json!({"name": "walk", "stats": {"2021-03-01": 2, "2021-03-02": 1}}),
+            json!({"name": "run", "stats": {"2021-03-01": {"value": 15, "precision": 1}}}),
+        ];
+        assert_eq!(
+            merge_habits(&base, &ours, &theirs),
+            vec![
+                json!({"name": "walk", "stats": {"2021-03-01": 4, "2021-03-02": 2}}),
+                json!({"name": "run", "stats": {"2021-03-01": {"value": 30, "precision": 1}}}),
+            ]
+        );
+    }
+
+    #[test]
+    fn conflicting_values_keep_ours() {
+        let base = vec![json!({"name": "walk", "goal": 1, "stats": {"2021-03-01": true}})];
+        let ours = vec![json!({"name": "walk", "goal": 3, "stats": {"2021-03-01": false}})];
+        let theirs = vec![json!({"name": "walk", "goal": 2, "stats": {}})];
+        assert_eq!(merge_habits(&base, &ours, &theirs), ours);
+
+        // habits deleted on disk and left alone by us are gone
+        assert!(merge_habits(&base, &base, &[]).is_empty());
+    }
+}
@@ -1,17 +1,22 @@
 use std::default::Default;
+use std::path::PathBuf;
 use std::sync::mpsc::Receiver;
 
+use cursive::Vec2;
 use notify::{DebouncedEvent, RecommendedWatcher};
 
 use crate::habit::HabitWrapper;
 
 mod cursor;
+mod history;
 mod impl_self;
 mod impl_view;
+mod merge;
 mod message;
 
 pub struct StatusLine(String, String);
-pub use cursor::Cursor;
+pub use cursor::{add_months, Cursor};
+pub use history::{Change, History};
 pub use message::{Message, MessageKind};
 
 pub struct App {
@@ -23,6 +28,26 @@ pub struct App {
     focus: usize,
     cursor: Cursor,
     message: Message,
+    history: History,
+
+    // size given to the grid by the last layout, the habits per
+    // row that fit in it, and the first row drawn
+    size: Vec2,
+    columns: usize,
+    scroll: usize,
+
+    // data files with unsaved changes
+    regular_modified: bool,
+    auto_modified: bool,
+
+    // corrupt data files that could not be set aside, these are
+    // never written so their contents are not lost
+    corrupt: Vec<PathBuf>,
+
+    // contents of the data files when last loaded or saved, changes
+    // made by others since are merged in on save
+    regular_on_disk: String,
+    auto_on_disk: String,
 }
 
 impl Default for App {
@@ -1,27 +1,49 @@
 use std::fmt;
 use std::str::FromStr;
 
+use chrono::{Datelike, Duration, Local, NaiveDate, Weekday};
 use cursive::event::{Event, EventResult, Key};
 use cursive::theme::{BaseColor, Color, ColorStyle};
 use cursive::view::Resizable;
 use cursive::views::{EditView, LinearLayout, OnEventView, TextView};
 use cursive::Cursive;
 
-use crate::app::App;
-use crate::utils::{GRID_WIDTH, VIEW_WIDTH};
+use crate::app::{add_months, App};
+use crate::export::ExportFormat;
+use crate::habit::{Period, Schedule};
+use crate::import::ImportFormat;
+use crate::stats;
+use crate::utils::VIEW_WIDTH;
 
 static COMMANDS: &'static [&'static str] = &[
     "add",
     "add-auto",
     "delete",
+    "archive",
+    "unarchive",
+    "rename",
+    "goal",
+    "schedule",
+    "excuse",
+    "unexcuse",
+    "note",
     "track-up",
     "track-down",
+    "track-set",
+    "export",
+    "import",
     "month-prev",
     "month-next",
+    "year-prev",
+    "year-next",
+    "goto",
+    "stats",
     "quit",
     "write",
     "help",
     "writeandquit",
+    "undo",
+    "redo",
 ];
 
 fn get_command_completion(prefix: &str) -> Option<String> {
@@ -34,10 +56,72 @@ fn get_habit_completion(prefix: &str, habit_names: &[String]) -> Option<String>
     return first_match.map(|x| x.into());
 }
 
+// Split `input` into words, along with the byte offset each word
+// starts at. Words are separated by whitespace, unless it is quoted
+// with '' or "", or escaped with a backslash. Within double quotes,
+// a backslash only escapes `"` and `\`. Also returns the quote
+// that is left open at the end of `input`, if any, and whether
+// `input` ends within a word.
+fn split_words(input: &str) -> (Vec<(usize, String)>, Option<char>, bool) {
+    let mut words: Vec<(usize, String)> = Vec::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = input.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        if !in_word {
+            if c.is_whitespace() {
+                continue;
+            }
+            words.push((idx, String::new()));
+            in_word = true;
+        }
+        let word = &mut words.last_mut().unwrap().1;
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => in_word = false,
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (Some('\''), c) => word.push(c),
+            (Some('"'), '\\') => match chars.peek() {
+                Some(&(_, e)) if e == '"' || e == '\\' => {
+                    word.push(e);
+                    chars.next();
+                }
+                _ => word.push('\\'),
+            },
+            (_, '\\') => {
+                if let Some((_, e)) = chars.next() {
+                    word.push(e);
+                }
+            }
+            (_, c) => word.push(c),
+        }
+    }
+    return (words, quote, in_word);
+}
+
+pub fn tokenize(input: &str) -> Result<Vec<String>> {
+    match split_words(input) {
+        (_, Some(q), _) => return Err(CommandLineError::UnclosedQuote(q)),
+        (words, None, _) => return Ok(words.into_iter().map(|(_, w)| w).collect()),
+    }
+}
+
+// quote `word` so that `tokenize` reads it back as a single word
+pub fn quote(word: &str) -> String {
+    if !word.is_empty()
+        && !word
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '\'' || c == '\\')
+    {
+        return word.into();
+    }
+    return format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""));
+}
+
 pub fn open_command_window(s: &mut Cursive) {
-    let habit_list: Vec<String> = s
+    let (habit_list, columns) = s
         .call_on_name("Main", |view: &mut App| {
-            return view.list_habits();
+            return (view.list_habits(), view.columns());
         })
         .unwrap();
     let style = ColorStyle::new(Color::Dark(BaseColor::Black), Color::Dark(BaseColor::White));
@@ -51,25 +135,25 @@ pub fn open_command_window(s: &mut Cursive) {
         Event::Key(Key::Tab),
         move |view: &mut EditView, _: &Event| {
             let contents = view.get_content();
-            if !contents.contains(" ") {
-                let completion = get_command_completion(&*contents);
-                if let Some(c) = completion {
-                    let cb = view.set_content(c);
-                    return Some(EventResult::Consumed(Some(cb)));
-                };
-                return None;
-            } else {
-                let word = contents.split(' ').last().unwrap();
-                let completion = get_habit_completion(word, &habit_list);
-                if let Some(c) = completion {
-                    let cb = view.set_content(format!("{}", contents) + &c[word.len()..]);
-                    return Some(EventResult::Consumed(Some(cb)));
-                };
-                return None;
+            let (mut words, _, in_word) = split_words(&contents);
+            // trailing whitespace starts a new, empty word
+            if !in_word {
+                words.push((contents.len(), String::new()));
             }
+            let (start, word) = words.last().unwrap();
+            let completion = if words.len() == 1 {
+                get_command_completion(word)
+            } else {
+                get_habit_completion(word, &habit_list).map(|c| quote(&c))
+            };
+            if let Some(c) = completion {
+                let cb = view.set_content(format!("{}{}", &contents[..*start], c));
+                return Some(EventResult::Consumed(Some(cb)));
+            };
+            return None;
         },
     )
-    .fixed_width(VIEW_WIDTH * GRID_WIDTH);
+    .fixed_width(VIEW_WIDTH * columns);
     s.call_on_name("Frame", |view: &mut LinearLayout| {
         let mut commandline = LinearLayout::horizontal()
             .child(TextView::new(":"))
@@ -90,6 +174,7 @@ fn call_on_app(s: &mut Cursive, input: &str) {
         let cmd = Command::from_string(input);
         view.clear_message();
         view.parse_command(cmd);
+        view.save_changes();
     });
     s.call_on_name("Frame", |view: &mut LinearLayout| {
         view.set_focus_index(0);
@@ -102,11 +187,12 @@ fn call_on_app(s: &mut Cursive, input: &str) {
     // TODO: fix this somehow
     match Command::from_string(input) {
         Ok(Command::Quit) | Ok(Command::WriteAndQuit) => s.quit(),
+        Ok(Command::Stats(name)) => stats::open_stats_window(s, &name),
         _ => {}
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum GoalKind {
     Count(u32),
     Bit,
@@ -144,19 +230,163 @@ impl FromStr for GoalKind {
     }
 }
 
+impl GoalKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GoalKind::Count(_) => "count",
+            GoalKind::Bit => "bit",
+            GoalKind::Float(_, _) => "float",
+            GoalKind::Addiction(_) => "addiction",
+        }
+    }
+}
+
+// the goal expression that parses back into this goal
+impl fmt::Display for GoalKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GoalKind::Count(n) => write!(f, "{}", n),
+            GoalKind::Bit => write!(f, "1"),
+            GoalKind::Float(v, p) => {
+                let scale = 10f64.powi(*p as i32);
+                write!(f, "{:.*}", *p as usize, *v as f64 / scale)
+            }
+            GoalKind::Addiction(n) => write!(f, "<{}", n),
+        }
+    }
+}
+
+// A goal along with the period it is to be reached in, written as
+// `3` (every day), `3/w` (every week) or `3/m` (every month)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Goal {
+    pub kind: GoalKind,
+    pub period: Period,
+}
+
+impl FromStr for Goal {
+    type Err = CommandLineError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, period) = match s.split_once('/') {
+            Some((kind, period)) => {
+                let period = match period.to_lowercase().as_ref() {
+                    "d" | "day" => Period::Day,
+                    "w" | "week" => Period::Week,
+                    "m" | "month" => Period::Month,
+                    _ => return Err(CommandLineError::InvalidGoal(s.into())),
+                };
+                (kind, period)
+            }
+            None => (s, Period::Day),
+        };
+        return Ok(Goal {
+            kind: GoalKind::from_str(kind)?,
+            period,
+        });
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.period {
+            Period::Day => write!(f, "{}", self.kind),
+            Period::Week => write!(f, "{}/w", self.kind),
+            Period::Month => write!(f, "{}/m", self.kind),
+        }
+    }
+}
+
+// `daily`, `weekdays`, `weekends`, weekdays as in `mon,wed,fri` or
+// `mon-fri`, or `every 3 days` (also `every 3d` or `3d`), counted
+// from today
+impl FromStr for Schedule {
+    type Err = CommandLineError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        let invalid = || CommandLineError::InvalidSchedule(s.clone());
+        match s.as_ref() {
+            "daily" | "every day" => return Ok(Schedule::Daily),
+            "weekdays" => {
+                let days = vec![
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                ];
+                return Ok(Schedule::Weekdays(days));
+            }
+            "weekends" => return Ok(Schedule::Weekdays(vec![Weekday::Sat, Weekday::Sun])),
+            _ => {}
+        }
+
+        let every = s.strip_prefix("every").unwrap_or(&s).trim();
+        if every.starts_with(|c: char| c.is_ascii_digit()) {
+            let digits = every
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>();
+            let n = digits.parse::<u32>().map_err(|_| invalid())?;
+            match every[digits.len()..].trim() {
+                "" | "d" | "day" | "days" if n > 0 => {
+                    let today = Local::now().naive_local().date();
+                    return Ok(Schedule::Every(n, today));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        let mut days = Vec::new();
+        for part in s.split(',') {
+            let (from, to) = part.split_once('-').unwrap_or((part, part));
+            let weekday = |d: &str| d.trim().parse::<Weekday>().map_err(|_| invalid());
+            let (mut day, to) = (weekday(from)?, weekday(to)?);
+            loop {
+                if !days.contains(&day) {
+                    days.push(day);
+                }
+                if day == to {
+                    break;
+                }
+                day = day.succ();
+            }
+        }
+        days.sort_by_key(|d| d.num_days_from_monday());
+        return Ok(Schedule::Weekdays(days));
+    }
+}
+
 #[derive(PartialEq)]
 pub enum Command {
-    Add(String, Option<GoalKind>, bool),
+    Add(String, Option<Goal>, bool),
     MonthPrev,
     MonthNext,
+    YearPrev,
+    YearNext,
+    Goto(NaiveDate),
+    Stats(String),
     Delete(String),
-    TrackUp(String),
-    TrackDown(String),
+    Archive(String),
+    Unarchive(String),
+    Rename(String, String),
+    Goal(String, Goal),
+    Schedule(String, Schedule),
+    Excuse(Option<String>, Option<NaiveDate>, bool), // habit (all if none), date, excused
+    Note(String, Option<NaiveDate>, String),         // habit, date, note
+    TrackUp(String, NaiveDate, u32),
+    TrackDown(String, NaiveDate, u32),
+    TrackSet(String, NaiveDate, String),
+    Export(ExportFormat, String),
+    Import(ImportFormat, String),
     Help(Option<String>),
     Write,
     Quit,
     Blank,
     WriteAndQuit,
+    Undo,
+    Redo,
 }
 
 #[derive(Debug)]
@@ -165,6 +395,14 @@ pub enum CommandLineError {
     InvalidArg(u32),            // position
     NotEnoughArgs(String, u32), // command name, required no. of args
     InvalidGoal(String),        // goal expression
+    InteractiveOnly(String),    // command name
+    UnknownFlag(String),        // flag
+    MissingValue(String),       // flag
+    InvalidValue(String),       // value
+    InvalidDate(String),        // date expression
+    UnknownFormat(String),      // format name
+    UnclosedQuote(char),        // quote character
+    InvalidSchedule(String),    // schedule expression
 }
 
 impl std::error::Error for CommandLineError {}
@@ -178,26 +416,150 @@ impl fmt::Display for CommandLineError {
                 write!(f, "Command `{}` requires atleast {} argument(s)!", s, n)
             }
             CommandLineError::InvalidGoal(s) => write!(f, "Invalid goal expression: `{}`", s),
+            CommandLineError::InteractiveOnly(s) => {
+                write!(f, "Command `{}` is only available in interactive mode", s)
+            }
+            CommandLineError::UnknownFlag(s) => write!(f, "Unknown flag: `{}`", s),
+            CommandLineError::MissingValue(s) => write!(f, "Flag `{}` requires a value", s),
+            CommandLineError::InvalidValue(s) => write!(f, "Invalid value: `{}`", s),
+            CommandLineError::InvalidDate(s) => write!(f, "Invalid date: `{}`", s),
+            CommandLineError::UnknownFormat(s) => write!(f, "Unknown format: `{}`", s),
+            CommandLineError::UnclosedQuote(q) => write!(f, "Unclosed quote: {}", q),
+            CommandLineError::InvalidSchedule(s) => write!(f, "Invalid schedule: `{}`", s),
         }
     }
 }
 
 type Result<T> = std::result::Result<T, CommandLineError>;
 
+// removes `flag` and the value following it from `args`
+fn take_flag(args: &mut Vec<String>, flag: &str) -> Result<Option<String>> {
+    match args.iter().position(|a| a == flag) {
+        Some(idx) => {
+            if idx + 1 >= args.len() {
+                return Err(CommandLineError::MissingValue(flag.into()));
+            }
+            let value = args.remove(idx + 1);
+            args.remove(idx);
+            return Ok(Some(value));
+        }
+        None => return Ok(None),
+    }
+}
+
+// dates are written as YYYY-MM-DD, or relative to today: `today`,
+// `yesterday`, an offset in days, weeks, months or years (`-3d`,
+// `-2w`, `-1m`, `-1y`), or a weekday (`monday` is today if it is a
+// monday, `last monday` is always before today). Dates may not lie
+// in the future.
+pub fn parse_date(s: &str) -> Result<NaiveDate> {
+    let today = Local::now().naive_local().date();
+    match relative_date(s, today) {
+        Some(d) if d <= today => Ok(d),
+        _ => Err(CommandLineError::InvalidDate(s.into())),
+    }
+}
+
+fn relative_date(s: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let s = s.trim().to_lowercase();
+    if let Ok(d) = NaiveDate::parse_from_str(&s, "%Y-%m-%d") {
+        return Some(d);
+    }
+    match s.as_ref() {
+        "today" => return Some(today),
+        "yesterday" => return today.pred_opt(),
+        _ => {}
+    }
+
+    // days back from today to the given weekday
+    let since = |weekday: Weekday| {
+        let (from, to) = (
+            today.weekday().num_days_from_monday(),
+            weekday.num_days_from_monday(),
+        );
+        (from + 7 - to) % 7
+    };
+    if let Some(weekday) = s.strip_prefix("last ") {
+        let days = match since(weekday.trim().parse::<Weekday>().ok()?) {
+            0 => 7,
+            n => n,
+        };
+        return today.checked_sub_signed(Duration::days(days as i64));
+    }
+    if let Ok(weekday) = s.parse::<Weekday>() {
+        return today.checked_sub_signed(Duration::days(since(weekday) as i64));
+    }
+
+    let offset = s.strip_prefix('-')?;
+    let unit = offset.chars().last()?;
+    let n = offset[..offset.len() - unit.len_utf8()]
+        .parse::<i32>()
+        .ok()?;
+    match unit {
+        'd' => return today.checked_sub_signed(Duration::days(n as i64)),
+        'w' => return today.checked_sub_signed(Duration::weeks(n as i64)),
+        'm' => return Some(add_months(today, -n)),
+        'y' => return Some(add_months(today, -12 * n)),
+        _ => return None,
+    }
+}
+
+// parses the arguments shared by track-up and track-down:
+// <habit> [--date YYYY-MM-DD] [--by N]
+fn track_args(first: String, mut args: Vec<String>) -> Result<(String, NaiveDate, u32)> {
+    let date = take_flag(&mut args, "--date")?
+        .map(|d| parse_date(&d))
+        .transpose()?
+        .unwrap_or_else(|| Local::now().naive_local().date());
+    let by = take_flag(&mut args, "--by")?
+        .map(|n| {
+            n.parse::<u32>()
+                .map_err(|_| CommandLineError::InvalidValue(n.clone()))
+        })
+        .transpose()?
+        .unwrap_or(1);
+    if let Some(flag) = args.iter().find(|a| a.starts_with("--")) {
+        return Err(CommandLineError::UnknownFlag(flag.into()));
+    }
+    if args.is_empty() {
+        return Err(CommandLineError::NotEnoughArgs(first, 1));
+    }
+    return Ok((args[0].to_string(), date, by));
+}
+
 impl Command {
+    // commands that act on the view rather than on habit data,
+    // these cannot be run through `dijo -c`
+    pub fn is_interactive(&self) -> bool {
+        matches!(
+            self,
+            Command::MonthPrev
+                | Command::MonthNext
+                | Command::YearPrev
+                | Command::YearNext
+                | Command::Goto(_)
+                | Command::Stats(_)
+                | Command::Quit
+                | Command::Write
+                | Command::WriteAndQuit
+                | Command::Undo
+                | Command::Redo
+        )
+    }
+
     pub fn from_string<P: AsRef<str>>(input: P) -> Result<Command> {
-        let mut strings: Vec<&str> = input.as_ref().trim().split(' ').collect();
+        let strings = tokenize(input.as_ref())?;
         if strings.is_empty() {
             return Ok(Command::Blank);
         }
 
         let first = strings.first().unwrap().to_string();
-        let mut args: Vec<String> = strings.iter_mut().skip(1).map(|s| s.to_string()).collect();
+        let mut args: Vec<String> = strings.into_iter().skip(1).collect();
         let mut _add = |auto: bool, first: String| {
             if args.is_empty() {
                 return Err(CommandLineError::NotEnoughArgs(first, 1));
             }
-            let goal = args.get(1).map(|x| GoalKind::from_str(x)).transpose()?;
+            let goal = args.get(1).map(|x| Goal::from_str(x)).transpose()?;
             return Ok(Command::Add(
                 args.get_mut(0).unwrap().to_string(),
                 goal,
@@ -214,17 +576,95 @@ impl Command {
                 }
                 return Ok(Command::Delete(args[0].to_string()));
             }
-            "track-up" | "tup" => {
+            "archive" | "ar" => {
                 if args.is_empty() {
                     return Err(CommandLineError::NotEnoughArgs(first, 1));
                 }
-                return Ok(Command::TrackUp(args[0].to_string()));
+                return Ok(Command::Archive(args[0].to_string()));
             }
-            "track-down" | "tdown" => {
+            "unarchive" | "unar" => {
+                if args.is_empty() {
+                    return Err(CommandLineError::NotEnoughArgs(first, 1));
+                }
+                return Ok(Command::Unarchive(args[0].to_string()));
+            }
+            "rename" | "rn" => {
+                if args.len() < 2 {
+                    return Err(CommandLineError::NotEnoughArgs(first, 2));
+                }
+                return Ok(Command::Rename(args[0].to_string(), args[1].to_string()));
+            }
+            "goal" | "g" => {
+                if args.len() < 2 {
+                    return Err(CommandLineError::NotEnoughArgs(first, 2));
+                }
+                let goal = Goal::from_str(&args[1])?;
+                return Ok(Command::Goal(args[0].to_string(), goal));
+            }
+            "schedule" | "sc" => {
+                if args.len() < 2 {
+                    return Err(CommandLineError::NotEnoughArgs(first, 2));
+                }
+                // schedules like `every 3 days` may span several words
+                let schedule = Schedule::from_str(&args[1..].join(" "))?;
+                return Ok(Command::Schedule(args[0].to_string(), schedule));
+            }
+            "excuse" | "ex" | "unexcuse" | "unex" => {
+                let excused = !first.starts_with("un");
+                let date = take_flag(&mut args, "--date")?
+                    .map(|d| parse_date(&d))
+                    .transpose()?;
+                if let Some(flag) = args.iter().find(|a| a.starts_with("--")) {
+                    return Err(CommandLineError::UnknownFlag(flag.into()));
+                }
+                return Ok(Command::Excuse(args.first().cloned(), date, excused));
+            }
+            "note" | "nt" => {
+                let date = take_flag(&mut args, "--date")?
+                    .map(|d| parse_date(&d))
+                    .transpose()?;
                 if args.is_empty() {
                     return Err(CommandLineError::NotEnoughArgs(first, 1));
                 }
-                return Ok(Command::TrackDown(args[0].to_string()));
+                // the note may span several words, none clears it
+                let note = args[1..].join(" ");
+                return Ok(Command::Note(args[0].to_string(), date, note));
+            }
+            "track-up" | "tup" => {
+                let (name, date, by) = track_args(first, args)?;
+                return Ok(Command::TrackUp(name, date, by));
+            }
+            "track-down" | "tdown" => {
+                let (name, date, by) = track_args(first, args)?;
+                return Ok(Command::TrackDown(name, date, by));
+            }
+            "track-set" | "tset" => {
+                let date = take_flag(&mut args, "--date")?
+                    .map(|d| parse_date(&d))
+                    .transpose()?
+                    .unwrap_or_else(|| Local::now().naive_local().date());
+                if args.len() < 2 {
+                    return Err(CommandLineError::NotEnoughArgs(first, 2));
+                }
+                return Ok(Command::TrackSet(
+                    args[0].to_string(),
+                    date,
+                    args[1].to_string(),
+                ));
+            }
+            "export" | "e" => {
+                if args.len() < 2 {
+                    return Err(CommandLineError::NotEnoughArgs(first, 2));
+                }
+                let format = ExportFormat::from_str(&args[0])?;
+                return Ok(Command::Export(format, args[1].to_string()));
+            }
+            "import" | "i" => {
+                if args.len() < 2 {
+                    return Err(CommandLineError::NotEnoughArgs(first, 2));
+                }
+                let format = ImportFormat::from_str(&args[0])?;
+                return Ok(Command::Import(format, args[1].to_string()));
             }
             "h" | "?" | "help" => {
                 if args.is_empty() {
@@ -234,11 +674,118 @@ impl Command {
             }
             "mprev" | "month-prev" => return Ok(Command::MonthPrev),
             "mnext" | "month-next" => return Ok(Command::MonthNext),
+            "yprev" | "year-prev" => return Ok(Command::YearPrev),
+            "ynext" | "year-next" => return Ok(Command::YearNext),
+            "goto" | "gt" => {
+                if args.is_empty() {
+                    return Err(CommandLineError::NotEnoughArgs(first, 1));
+                }
+                // relative dates may span several words
+                return Ok(Command::Goto(parse_date(&args.join(" "))?));
+            }
+            "stats" | "st" => {
+                if args.is_empty() {
+                    return Err(CommandLineError::NotEnoughArgs(first, 1));
+                }
+                return Ok(Command::Stats(args[0].to_string()));
+            }
             "wq" | "writeandquit" => return Ok(Command::WriteAndQuit),
             "q" | "quit" => return Ok(Command::Quit),
             "w" | "write" => return Ok(Command::Write),
+            "u" | "undo" => return Ok(Command::Undo),
+            "redo" => return Ok(Command::Redo),
             "" => return Ok(Command::Blank),
             s => return Err(CommandLineError::InvalidCommand(s.into())),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_words() {
+        let words = tokenize(r#"add "read a book"  30 it\'s 'a "b"' "c \"d\" \e""#).unwrap();
+        assert_eq!(
+            words,
+            vec!["add", "read a book", "30", "it's", "a \"b\"", "c \"d\" \\e"]
+        );
+        assert!(tokenize("add \"read a book").is_err());
+    }
+
+    #[test]
+    fn quote_round_trip() {
+        for name in &["walk", "read a book", "it's \"fine\"", "a\\b", ""] {
+            assert_eq!(tokenize(&quote(name)).unwrap(), vec![name.to_string()]);
+        }
+    }
+
+    #[test]
+    fn track_flags_in_any_order() {
+        let date = NaiveDate::from_ymd(2020, 2, 29);
+        let expected = Command::TrackUp("walk".into(), date, 3);
+        let a = Command::from_string("track-up walk --date 2020-02-29 --by 3").unwrap();
+        let b = Command::from_string("track-up --by 3 walk --date 2020-02-29").unwrap();
+        assert!(a == expected);
+        assert!(b == expected);
+    }
+
+    #[test]
+    fn track_flag_errors() {
+        assert!(Command::from_string("track-up walk --by").is_err());
+        assert!(Command::from_string("track-up walk --by -1").is_err());
+        assert!(Command::from_string("track-down walk --date 2020-02-30").is_err());
+        assert!(Command::from_string("track-down walk --since 2020-02-20").is_err());
+        assert!(Command::from_string("track-set walk").is_err());
+    }
+
+    #[test]
+    fn goal_periods() {
+        let goal = Goal::from_str("3/w").unwrap();
+        assert_eq!(goal.kind, GoalKind::Count(3));
+        assert_eq!(goal.period, Period::Week);
+        assert_eq!(Goal::from_str("1/month").unwrap().period, Period::Month);
+        assert_eq!(Goal::from_str("<2").unwrap().period, Period::Day);
+        assert!(Goal::from_str("3/y").is_err());
+        for expr in &["3", "1/w", "2.5/m", "<4/w"] {
+            assert_eq!(Goal::from_str(expr).unwrap().to_string(), *expr);
+        }
+    }
+
+    #[test]
+    fn schedules() {
+        let workdays = Schedule::from_str("mon-fri").unwrap();
+        assert_eq!(workdays, Schedule::from_str("weekdays").unwrap());
+        assert_eq!(workdays.to_string(), "mon,tue,wed,thu,fri");
+        let days = Schedule::from_str("sat,mon,sun-mon").unwrap();
+        assert_eq!(days.to_string(), "mon,sat,sun");
+        assert!(Schedule::from_str("every 3 days")
+            .unwrap()
+            .is_due(Local::now().naive_local().date()));
+        assert_eq!(
+            Schedule::from_str("every 2d").unwrap().to_string(),
+            "every 2 days"
+        );
+        assert!(Schedule::from_str("every 0 days").is_err());
+        assert!(Schedule::from_str("mon-funday").is_err());
+    }
+
+    #[test]
+    fn relative_dates() {
+        // a tuesday
+        let today = NaiveDate::from_ymd(2020, 3, 31);
+        let date = |s| relative_date(s, today);
+        assert_eq!(date("2020-01-05"), Some(NaiveDate::from_ymd(2020, 1, 5)));
+        assert_eq!(date("yesterday"), Some(NaiveDate::from_ymd(2020, 3, 30)));
+        assert_eq!(date("-3d"), Some(NaiveDate::from_ymd(2020, 3, 28)));
+        assert_eq!(date("-2w"), Some(NaiveDate::from_ymd(2020, 3, 17)));
+        assert_eq!(date("-1m"), Some(NaiveDate::from_ymd(2020, 2, 29)));
+        assert_eq!(date("-1y"), Some(NaiveDate::from_ymd(2019, 3, 31)));
+        assert_eq!(date("tuesday"), Some(today));
+        assert_eq!(date("last Tuesday"), Some(NaiveDate::from_ymd(2020, 3, 24)));
+        assert_eq!(date("last mon"), Some(NaiveDate::from_ymd(2020, 3, 30)));
+        assert_eq!(date("-3x"), None);
+        assert_eq!(date("last week"), None);
+    }
+}
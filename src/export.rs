@@ -0,0 +1,203 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::command::CommandLineError;
+use crate::habit::HabitWrapper;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+    Markdown,
+}
+
+impl FromStr for ExportFormat {
+    type Err = CommandLineError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" | "json" => Ok(ExportFormat::Jsonl),
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            _ => Err(CommandLineError::UnknownFormat(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportFormat::Csv => write!(f, "csv"),
+            ExportFormat::Jsonl => write!(f, "jsonl"),
+            ExportFormat::Markdown => write!(f, "markdown"),
+        }
+    }
+}
+
+// A single tracked entry of a habit, or a note on an untracked day
+#[derive(Debug, Serialize)]
+pub struct Row {
+    pub date: NaiveDate,
+    pub habit: String,
+    pub kind: String,
+    pub value: String,
+    pub goal: String,
+    pub reached: bool,
+    pub note: String,
+}
+
+const HEADER: [&str; 7] = ["date", "habit", "kind", "value", "goal", "reached", "note"];
+
+impl Row {
+    fn fields(&self) -> [String; 7] {
+        [
+            self.date.to_string(),
+            self.habit.clone(),
+            self.kind.clone(),
+            self.value.clone(),
+            self.goal.clone(),
+            self.reached.to_string(),
+            self.note.clone(),
+        ]
+    }
+}
+
+// every tracked or noted day of every habit, ordered by date and
+// then by the order of the habits
+pub fn rows(habits: &[Box<dyn HabitWrapper>]) -> Vec<Row> {
+    let mut rows = habits
+        .iter()
+        .flat_map(|h| {
+            let kind = h.kind();
+            let mut dates = h.tracked_dates();
+            dates.extend(h.noted_dates());
+            dates.sort();
+            dates.dedup();
+            dates
+                .into_iter()
+                .map(|d| Row {
+                    date: d,
+                    habit: h.name(),
+                    kind: kind.name().into(),
+                    value: h.value(d).unwrap_or_default(),
+                    // the goal that `reached` is judged against
+                    goal: h.goal_expr_on(d).to_string(),
+                    reached: h.reached_goal(d),
+                    note: h.note(d).unwrap_or_default(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    rows.sort_by_key(|r| r.date);
+    return rows;
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", s.replace('"', "\"\""));
+    }
+    return s.into();
+}
+
+fn markdown_field(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', " ")
+}
+
+pub fn write_rows(format: ExportFormat, rows: &[Row], out: &mut impl Write) -> io::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(out, "{}", HEADER.join(","))?;
+            for row in rows {
+                let fields = row
+                    .fields()
+                    .iter()
+                    .map(|f| csv_field(f))
+                    .collect::<Vec<_>>();
+                writeln!(out, "{}", fields.join(","))?;
+            }
+        }
+        ExportFormat::Jsonl => {
+            for row in rows {
+                serde_json::to_writer(&mut *out, row)?;
+                writeln!(out)?;
+            }
+        }
+        ExportFormat::Markdown => {
+            writeln!(out, "| {} |", HEADER.join(" | "))?;
+            writeln!(out, "|{}", "---|".repeat(HEADER.len()))?;
+            for row in rows {
+                let fields = row
+                    .fields()
+                    .iter()
+                    .map(|f| markdown_field(f))
+                    .collect::<Vec<_>>();
+                writeln!(out, "| {} |", fields.join(" | "))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{Goal, GoalKind};
+    use crate::habit::{Count, Period};
+    use chrono::{Duration, Local};
+
+    fn row() -> Row {
+        Row {
+            date: NaiveDate::from_ymd(2020, 6, 1),
+            habit: "read, \"slowly\"".into(),
+            kind: "count".into(),
+            value: "3".into(),
+            goal: "5".into(),
+            reached: false,
+            note: "late, tired".into(),
+        }
+    }
+
+    #[test]
+    fn csv_quotes_fields() {
+        let mut out = Vec::new();
+        write_rows(ExportFormat::Csv, &[row()], &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "date,habit,kind,value,goal,reached,note\n\
+             2020-06-01,\"read, \"\"slowly\"\"\",count,3,5,false,\"late, tired\"\n"
+        );
+    }
+
+    #[test]
+    fn jsonl_one_object_per_line() {
+        let mut out = Vec::new();
+        write_rows(ExportFormat::Jsonl, &[row(), row()], &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 2);
+        let value: serde_json::Value = serde_json::from_str(out.lines().next().unwrap()).unwrap();
+        assert_eq!(value["date"], "2020-06-01");
+        assert_eq!(value["reached"], false);
+    }
+
+    #[test]
+    fn goal_column_is_the_goal_in_force() {
+        let today = Local::now().naive_local().date();
+        let earlier = today - Duration::days(3);
+        let mut h: Box<dyn HabitWrapper> = Box::new(Count::new("walk", 2, false));
+        h.set_value(earlier, "2");
+        h.set_value(today, "2");
+        h.set_goal_kind(GoalKind::Count(5));
+        h.set_period(Period::Week);
+        assert_eq!(h.goal_expr(), Goal::from_str("5/w").unwrap());
+
+        let goals = rows(&[h])
+            .into_iter()
+            .map(|r| (r.goal, r.reached))
+            .collect::<Vec<_>>();
+        assert_eq!(goals, vec![("2".into(), true), ("5/w".into(), false)]);
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::command::GoalKind;
+use crate::habit::metadata::Metadata;
+use crate::habit::prelude::replace_goal;
+use crate::habit::traits::Habit;
+use crate::habit::TrackEvent;
+
+// An addiction is the inverse of a count: the tracked value should
+// stay at or below `limit`, and untracked days are clean days.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Addiction {
+    name: String,
+    stats: HashMap<NaiveDate, u32>,
+    limit: u32,
+
+    // earlier goals, keyed by the date they were replaced on
+    #[serde(default)]
+    goal_history: BTreeMap<NaiveDate, u32>,
+
+    #[serde(flatten)]
+    meta: Metadata,
+}
+
+impl Addiction {
+    pub fn new(name: impl AsRef<str>, limit: u32, auto: bool) -> Self {
+        return Addiction {
+            name: name.as_ref().to_owned(),
+            stats: HashMap::new(),
+            limit,
+            goal_history: BTreeMap::new(),
+            meta: Metadata::new(auto),
+        };
+    }
+}
+
+impl Habit for Addiction {
+    type HabitType = u32;
+
+    fn name(&self) -> String {
+        return self.name.clone();
+    }
+    fn set_name(&mut self, n: impl AsRef<str>) {
+        self.name = n.as_ref().to_owned();
+    }
+    fn goal_kind(&self, goal: Self::HabitType) -> GoalKind {
+        GoalKind::Addiction(goal)
+    }
+    fn set_goal(&mut self, g: Self::HabitType) {
+        replace_goal(&mut self.goal_history, self.limit, g);
+        self.limit = g;
+    }
+    fn current_goal(&self) -> Self::HabitType {
+        self.limit
+    }
+    fn goal_history(&self) -> &BTreeMap<NaiveDate, Self::HabitType> {
+        &self.goal_history
+    }
+    fn goal_from_kind(&self, goal: GoalKind) -> Option<Self::HabitType> {
+        match goal {
+            GoalKind::Addiction(n) => Some(n),
+            _ => None,
+        }
+    }
+    fn get_by_date(&self, date: NaiveDate) -> Option<&Self::HabitType> {
+        self.stats.get(&date)
+    }
+    fn tracked_dates(&self) -> Vec<NaiveDate> {
+        let mut dates = self.stats.keys().copied().collect::<Vec<_>>();
+        dates.sort();
+        return dates;
+    }
+    fn insert_entry(&mut self, date: NaiveDate, val: Self::HabitType) {
+        *self.stats.entry(date).or_insert(val) = val;
+        self.meta.tracked(date);
+    }
+    fn remove_entry(&mut self, date: NaiveDate) {
+        self.stats.remove(&date);
+        self.meta.untracked(date);
+    }
+    fn parse_value(&self, value: &str) -> Option<Self::HabitType> {
+        value.parse::<u32>().ok()
+    }
+    fn format_value(&self, value: &Self::HabitType) -> String {
+        value.to_string()
+    }
+    fn as_number(&self, _: &Self::HabitType) -> Option<f64> {
+        None
+    }
+    fn reached_goal(&self, date: NaiveDate) -> bool {
+        let values = self.period_values(date);
+        if !values.is_empty() {
+            return values.into_iter().sum::<u32>() <= self.goal_on(date);
+        }
+        // days that have not happened yet cannot be clean
+        return date <= Local::now().naive_local().date();
+    }
+    fn remaining(&self, date: NaiveDate) -> u32 {
+        if !self.is_active(date) {
+            return 0;
+        }
+        // every period is a single goal: staying within the limit
+        if self.reached_goal(date) {
+            return 0;
+        } else {
+            return 1;
+        }
+    }
+    fn goal(&self) -> u32 {
+        return 1;
+    }
+    fn goal_units_on(&self, _: NaiveDate) -> u32 {
+        return 1;
+    }
+    fn meta(&self) -> &Metadata {
+        &self.meta
+    }
+    fn meta_mut(&mut self) -> &mut Metadata {
+        &mut self.meta
+    }
+    fn modify(&mut self, date: NaiveDate, event: TrackEvent) {
+        if let Some(val) = self.stats.get_mut(&date) {
+            match event {
+                TrackEvent::Increment => *val += 1,
+                TrackEvent::Decrement => {
+                    if *val > 0 {
+                        *val -= 1
+                    } else {
+                        self.remove_entry(date);
+                    };
+                }
+            }
+        } else {
+            match event {
+                TrackEvent::Increment => self.insert_entry(date, 1),
+                _ => {}
+            };
+        }
+    }
+}
@@ -1,16 +1,16 @@
-use std::collections::HashMap;
-use std::default::Default;
+use std::collections::{BTreeMap, HashMap};
 
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
 use crate::command::GoalKind;
-use crate::habit::prelude::default_auto;
+use crate::habit::metadata::Metadata;
+use crate::habit::prelude::replace_goal;
 use crate::habit::traits::Habit;
-use crate::habit::{InnerData, TrackEvent};
+use crate::habit::TrackEvent;
 use crate::CONFIGURATION;
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct CustomBool(bool);
 
 use std::fmt;
@@ -40,11 +40,12 @@ pub struct Bit {
     stats: HashMap<NaiveDate, CustomBool>,
     goal: CustomBool,
 
-    #[serde(default = "default_auto")]
-    auto: bool,
+    // earlier goals, keyed by the date they were replaced on
+    #[serde(default)]
+    goal_history: BTreeMap<NaiveDate, CustomBool>,
 
-    #[serde(skip)]
-    inner_data: InnerData,
+    #[serde(flatten)]
+    meta: Metadata,
 }
 
 impl Bit {
@@ -53,8 +54,8 @@ impl Bit {
             name: name.as_ref().to_owned(),
             stats: HashMap::new(),
             goal: CustomBool(true),
-            auto,
-            inner_data: Default::default(),
+            goal_history: BTreeMap::new(),
+            meta: Metadata::new(auto),
         };
     }
 }
@@ -67,33 +68,68 @@ impl Habit for Bit {
     fn set_name(&mut self, n: impl AsRef<str>) {
         self.name = n.as_ref().to_owned();
     }
-    fn kind(&self) -> GoalKind {
+    fn goal_kind(&self, _: Self::HabitType) -> GoalKind {
         GoalKind::Bit
     }
     fn set_goal(&mut self, g: Self::HabitType) {
+        replace_goal(&mut self.goal_history, self.goal, g);
         self.goal = g;
     }
+    fn current_goal(&self) -> Self::HabitType {
+        self.goal
+    }
+    fn goal_history(&self) -> &BTreeMap<NaiveDate, Self::HabitType> {
+        &self.goal_history
+    }
+    fn goal_from_kind(&self, goal: GoalKind) -> Option<Self::HabitType> {
+        match goal {
+            GoalKind::Bit => Some(CustomBool(true)),
+            _ => None,
+        }
+    }
     fn get_by_date(&self, date: NaiveDate) -> Option<&Self::HabitType> {
         self.stats.get(&date)
     }
+    fn tracked_dates(&self) -> Vec<NaiveDate> {
+        let mut dates = self.stats.keys().copied().collect::<Vec<_>>();
+        dates.sort();
+        return dates;
+    }
     fn insert_entry(&mut self, date: NaiveDate, val: Self::HabitType) {
         *self.stats.entry(date).or_insert(val) = val;
+        self.meta.tracked(date);
     }
-    fn reached_goal(&self, date: NaiveDate) -> bool {
-        if let Some(val) = self.stats.get(&date) {
-            if val.0 >= self.goal.0 {
-                return true;
-            }
+    fn remove_entry(&mut self, date: NaiveDate) {
+        self.stats.remove(&date);
+        self.meta.untracked(date);
+    }
+    fn parse_value(&self, value: &str) -> Option<Self::HabitType> {
+        match value.to_lowercase().as_ref() {
+            "1" | "y" | "yes" | "true" => Some(CustomBool(true)),
+            "0" | "n" | "no" | "false" => Some(CustomBool(false)),
+            _ => None,
         }
-        return false;
+    }
+    fn format_value(&self, value: &Self::HabitType) -> String {
+        if value.0 { "1" } else { "0" }.to_string()
+    }
+    fn as_number(&self, _: &Self::HabitType) -> Option<f64> {
+        None
+    }
+    fn reached_goal(&self, date: NaiveDate) -> bool {
+        // a single day within the period is enough
+        let goal = self.goal_on(date);
+        return self
+            .period_values(date)
+            .into_iter()
+            .any(|val| val.0 >= goal.0);
     }
     fn remaining(&self, date: NaiveDate) -> u32 {
-        if let Some(val) = self.stats.get(&date) {
-            if val.0 {
-                return 0;
-            } else {
-                return 1;
-            }
+        if !self.is_active(date) {
+            return 0;
+        }
+        if self.reached_goal(date) {
+            return 0;
         } else {
             return 1;
         }
@@ -101,6 +137,15 @@ impl Habit for Bit {
     fn goal(&self) -> u32 {
         return 1;
     }
+    fn goal_units_on(&self, _: NaiveDate) -> u32 {
+        return 1;
+    }
+    fn meta(&self) -> &Metadata {
+        &self.meta
+    }
+    fn meta_mut(&mut self) -> &mut Metadata {
+        &mut self.meta
+    }
     fn modify(&mut self, date: NaiveDate, event: TrackEvent) {
         if let Some(val) = self.stats.get_mut(&date) {
             match event {
@@ -109,7 +154,7 @@ impl Habit for Bit {
                     if val.0 {
                         *val = false.into();
                     } else {
-                        self.stats.remove(&date);
+                        self.remove_entry(date);
                     }
                 }
             }
@@ -119,13 +164,4 @@ impl Habit for Bit {
             }
         }
     }
-    fn inner_data_ref(&self) -> &InnerData {
-        &self.inner_data
-    }
-    fn inner_data_mut_ref(&mut self) -> &mut InnerData {
-        &mut self.inner_data
-    }
-    fn is_auto(&self) -> bool {
-        self.auto
-    }
 }
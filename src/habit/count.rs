@@ -1,13 +1,13 @@
-use std::collections::HashMap;
-use std::default::Default;
+use std::collections::{BTreeMap, HashMap};
 
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
 use crate::command::GoalKind;
-use crate::habit::prelude::default_auto;
+use crate::habit::metadata::Metadata;
+use crate::habit::prelude::replace_goal;
 use crate::habit::traits::Habit;
-use crate::habit::{InnerData, TrackEvent};
+use crate::habit::TrackEvent;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Count {
@@ -15,11 +15,12 @@ pub struct Count {
     stats: HashMap<NaiveDate, u32>,
     goal: u32,
 
-    #[serde(default = "default_auto")]
-    auto: bool,
+    // earlier goals, keyed by the date they were replaced on
+    #[serde(default)]
+    goal_history: BTreeMap<NaiveDate, u32>,
 
-    #[serde(skip)]
-    inner_data: InnerData,
+    #[serde(flatten)]
+    meta: Metadata,
 }
 
 impl Count {
@@ -28,8 +29,8 @@ impl Count {
             name: name.as_ref().to_owned(),
             stats: HashMap::new(),
             goal,
-            auto,
-            inner_data: Default::default(),
+            goal_history: BTreeMap::new(),
+            meta: Metadata::new(auto),
         };
     }
 }
@@ -43,40 +44,75 @@ impl Habit for Count {
     fn set_name(&mut self, n: impl AsRef<str>) {
         self.name = n.as_ref().to_owned();
     }
-    fn kind(&self) -> GoalKind {
-        GoalKind::Count(self.goal)
+    fn goal_kind(&self, goal: Self::HabitType) -> GoalKind {
+        GoalKind::Count(goal)
     }
     fn set_goal(&mut self, g: Self::HabitType) {
+        replace_goal(&mut self.goal_history, self.goal, g);
         self.goal = g;
     }
+    fn current_goal(&self) -> Self::HabitType {
+        self.goal
+    }
+    fn goal_history(&self) -> &BTreeMap<NaiveDate, Self::HabitType> {
+        &self.goal_history
+    }
+    fn goal_from_kind(&self, goal: GoalKind) -> Option<Self::HabitType> {
+        match goal {
+            GoalKind::Count(n) => Some(n),
+            // a goal of 1 reads as a bit goal
+            GoalKind::Bit => Some(1),
+            _ => None,
+        }
+    }
     fn get_by_date(&self, date: NaiveDate) -> Option<&Self::HabitType> {
         self.stats.get(&date)
     }
+    fn tracked_dates(&self) -> Vec<NaiveDate> {
+        let mut dates = self.stats.keys().copied().collect::<Vec<_>>();
+        dates.sort();
+        return dates;
+    }
     fn insert_entry(&mut self, date: NaiveDate, val: Self::HabitType) {
         *self.stats.entry(date).or_insert(val) = val;
+        self.meta.tracked(date);
+    }
+    fn remove_entry(&mut self, date: NaiveDate) {
+        self.stats.remove(&date);
+        self.meta.untracked(date);
+    }
+    fn parse_value(&self, value: &str) -> Option<Self::HabitType> {
+        value.parse::<u32>().ok()
+    }
+    fn format_value(&self, value: &Self::HabitType) -> String {
+        value.to_string()
+    }
+    fn as_number(&self, value: &Self::HabitType) -> Option<f64> {
+        Some(*value as f64)
     }
     fn reached_goal(&self, date: NaiveDate) -> bool {
-        if let Some(val) = self.stats.get(&date) {
-            if val >= &self.goal {
-                return true;
-            }
-        }
-        return false;
+        let values = self.period_values(date);
+        return !values.is_empty() && values.into_iter().sum::<u32>() >= self.goal_on(date);
     }
     fn remaining(&self, date: NaiveDate) -> u32 {
-        if self.reached_goal(date) {
+        if !self.is_active(date) {
             return 0;
-        } else {
-            if let Some(val) = self.stats.get(&date) {
-                return self.goal - val;
-            } else {
-                return self.goal;
-            }
         }
+        let total = self.period_values(date).into_iter().sum::<u32>();
+        return self.goal_on(date).saturating_sub(total);
     }
     fn goal(&self) -> u32 {
         return self.goal;
     }
+    fn goal_units_on(&self, date: NaiveDate) -> u32 {
+        return self.goal_on(date);
+    }
+    fn meta(&self) -> &Metadata {
+        &self.meta
+    }
+    fn meta_mut(&mut self) -> &mut Metadata {
+        &mut self.meta
+    }
     fn modify(&mut self, date: NaiveDate, event: TrackEvent) {
         if let Some(val) = self.stats.get_mut(&date) {
             match event {
@@ -85,7 +121,7 @@ impl Habit for Count {
                     if *val > 0 {
                         *val -= 1
                     } else {
-                        self.stats.remove(&date);
+                        self.remove_entry(date);
                     };
                 }
             }
@@ -96,13 +132,4 @@ impl Habit for Count {
             };
         }
     }
-    fn inner_data_ref(&self) -> &InnerData {
-        &self.inner_data
-    }
-    fn inner_data_mut_ref(&mut self) -> &mut InnerData {
-        &mut self.inner_data
-    }
-    fn is_auto(&self) -> bool {
-        self.auto
-    }
 }
@@ -1,6 +1,5 @@
 use std::cmp::{Eq, Ord, PartialEq};
-use std::collections::HashMap;
-use std::default::Default;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::ops::{Add, Sub};
 
@@ -8,9 +7,10 @@ use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
 use crate::command::GoalKind;
-use crate::habit::prelude::default_auto;
+use crate::habit::metadata::Metadata;
+use crate::habit::prelude::replace_goal;
 use crate::habit::traits::Habit;
-use crate::habit::{InnerData, TrackEvent};
+use crate::habit::TrackEvent;
 
 #[derive(Copy, Clone, Debug, Ord, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct FloatData {
@@ -33,6 +33,11 @@ impl FloatData {
         };
         self - f
     }
+    // the value written out in full, as in `2.50`
+    pub fn to_decimal(self) -> String {
+        let scale = 10f64.powi(self.precision as i32);
+        format!("{:.*}", self.precision as usize, self.value as f64 / scale)
+    }
     pub fn zero() -> Self {
         FloatData {
             value: 0,
@@ -83,11 +88,13 @@ pub struct Float {
     stats: HashMap<NaiveDate, FloatData>,
     goal: FloatData,
     precision: u8,
-    #[serde(default = "default_auto")]
-    auto: bool,
 
-    #[serde(skip)]
-    inner_data: InnerData,
+    // earlier goals, keyed by the date they were replaced on
+    #[serde(default)]
+    goal_history: BTreeMap<NaiveDate, FloatData>,
+
+    #[serde(flatten)]
+    meta: Metadata,
 }
 
 impl Float {
@@ -100,8 +107,8 @@ impl Float {
                 precision,
             },
             precision,
-            auto,
-            inner_data: Default::default(),
+            goal_history: BTreeMap::new(),
+            meta: Metadata::new(auto),
         };
     }
 }
@@ -115,40 +122,95 @@ impl Habit for Float {
     fn set_name(&mut self, n: impl AsRef<str>) {
         self.name = n.as_ref().to_owned();
     }
-    fn kind(&self) -> GoalKind {
-        GoalKind::Float(self.goal.value, self.goal.precision)
+    fn goal_kind(&self, goal: Self::HabitType) -> GoalKind {
+        GoalKind::Float(goal.value, goal.precision)
     }
     fn set_goal(&mut self, g: Self::HabitType) {
+        replace_goal(&mut self.goal_history, self.goal, g);
         self.goal = g;
     }
+    fn current_goal(&self) -> Self::HabitType {
+        self.goal
+    }
+    fn goal_history(&self) -> &BTreeMap<NaiveDate, Self::HabitType> {
+        &self.goal_history
+    }
+    fn goal_from_kind(&self, goal: GoalKind) -> Option<Self::HabitType> {
+        // tracked values keep the precision of the habit, so the
+        // goal is scaled to it
+        let (value, precision) = match goal {
+            GoalKind::Float(v, p) => (v, p),
+            GoalKind::Count(n) => (n, 0),
+            GoalKind::Bit => (1, 0),
+            _ => return None,
+        };
+        let scale = 10f64.powi(self.precision as i32 - precision as i32);
+        return Some(FloatData {
+            value: (value as f64 * scale).round() as u32,
+            precision: self.precision,
+        });
+    }
     fn get_by_date(&self, date: NaiveDate) -> Option<&Self::HabitType> {
         self.stats.get(&date)
     }
+    fn tracked_dates(&self) -> Vec<NaiveDate> {
+        let mut dates = self.stats.keys().copied().collect::<Vec<_>>();
+        dates.sort();
+        return dates;
+    }
     fn insert_entry(&mut self, date: NaiveDate, val: Self::HabitType) {
         *self.stats.entry(date).or_insert(val) = val;
+        self.meta.tracked(date);
     }
-    fn reached_goal(&self, date: NaiveDate) -> bool {
-        if let Some(val) = self.stats.get(&date) {
-            if val >= &self.goal {
-                return true;
-            }
+    fn remove_entry(&mut self, date: NaiveDate) {
+        self.stats.remove(&date);
+        self.meta.untracked(date);
+    }
+    fn parse_value(&self, value: &str) -> Option<Self::HabitType> {
+        // values are stored as integers scaled by the precision
+        let v = value.parse::<f64>().ok()?;
+        if !v.is_finite() || v < 0. {
+            return None;
         }
-        return false;
+        return Some(FloatData {
+            value: (v * 10f64.powi(self.precision as i32)).round() as u32,
+            precision: self.precision,
+        });
+    }
+    fn format_value(&self, value: &Self::HabitType) -> String {
+        value.to_decimal()
+    }
+    fn as_number(&self, value: &Self::HabitType) -> Option<f64> {
+        Some(value.value as f64 / 10f64.powi(value.precision as i32))
+    }
+    fn reached_goal(&self, date: NaiveDate) -> bool {
+        let values = self.period_values(date);
+        let total = values.iter().map(|v| v.value).sum::<u32>();
+        return !values.is_empty() && total >= self.goal_on(date).value;
     }
     fn remaining(&self, date: NaiveDate) -> u32 {
-        if self.reached_goal(date) {
+        if !self.is_active(date) {
             return 0;
-        } else {
-            if let Some(&val) = self.stats.get(&date) {
-                return (self.goal - val).value;
-            } else {
-                return self.goal.value;
-            }
         }
+        let total = self
+            .period_values(date)
+            .into_iter()
+            .map(|v| v.value)
+            .sum::<u32>();
+        return self.goal_on(date).value.saturating_sub(total);
     }
     fn goal(&self) -> u32 {
         return self.goal.value;
     }
+    fn goal_units_on(&self, date: NaiveDate) -> u32 {
+        return self.goal_on(date).value;
+    }
+    fn meta(&self) -> &Metadata {
+        &self.meta
+    }
+    fn meta_mut(&mut self) -> &mut Metadata {
+        &mut self.meta
+    }
     fn modify(&mut self, date: NaiveDate, event: TrackEvent) {
         if let Some(val) = self.stats.get_mut(&date) {
             match event {
@@ -157,7 +219,7 @@ impl Habit for Float {
                     if *val > FloatData::zero() {
                         *val = val.sub(1);
                     } else {
-                        self.stats.remove(&date);
+                        self.remove_entry(date);
                     };
                 }
             }
@@ -174,13 +236,4 @@ impl Habit for Float {
             };
         }
     }
-    fn inner_data_ref(&self) -> &InnerData {
-        &self.inner_data
-    }
-    fn inner_data_mut_ref(&mut self) -> &mut InnerData {
-        &mut self.inner_data
-    }
-    fn is_auto(&self) -> bool {
-        self.auto
-    }
 }
@@ -0,0 +1,124 @@
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::default::Default;
+
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::habit::prelude::default_auto;
+use crate::habit::{InnerData, Period, Schedule};
+
+// Everything a habit keeps besides its name, goal and tracked
+// values, alike for every kind of habit. Saved as part of the habit
+// itself, its accessors are default methods of `Habit`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Metadata {
+    #[serde(default = "default_auto")]
+    pub auto: bool,
+
+    #[serde(default)]
+    pub archived: bool,
+
+    #[serde(default)]
+    pub created: Option<NaiveDate>,
+
+    #[serde(default)]
+    pub period: Period,
+
+    #[serde(default)]
+    pub schedule: Schedule,
+
+    #[serde(default)]
+    pub excused: BTreeSet<NaiveDate>,
+
+    #[serde(default)]
+    pub notes: BTreeMap<NaiveDate, String>,
+
+    // earlier periods, keyed by the date they were replaced on
+    #[serde(default)]
+    pub period_history: BTreeMap<NaiveDate, Period>,
+
+    #[serde(skip)]
+    pub inner_data: InnerData,
+
+    // the first day of the habit, see `Habit::created`. Found once
+    // it is first asked for (until then it is `None`), and kept up
+    // to date from then on.
+    #[serde(skip)]
+    pub start: Cell<Option<Option<NaiveDate>>>,
+}
+
+impl Metadata {
+    pub fn new(auto: bool) -> Self {
+        return Metadata {
+            auto,
+            archived: false,
+            created: Some(Local::now().naive_local().date()),
+            period: Period::Day,
+            schedule: Schedule::Daily,
+            excused: BTreeSet::new(),
+            notes: BTreeMap::new(),
+            period_history: BTreeMap::new(),
+            inner_data: Default::default(),
+            start: Cell::new(None),
+        };
+    }
+
+    // `date` was tracked, it is the new start if it comes first
+    pub fn tracked(&mut self, date: NaiveDate) {
+        if let Some(start) = self.start.get() {
+            if start.is_none() || start > Some(date) {
+                self.start.set(Some(Some(date)));
+            }
+        }
+    }
+
+    // `date` is no longer tracked, if the habit started on it the
+    // start is found again
+    pub fn untracked(&mut self, date: NaiveDate) {
+        if self.start.get() == Some(Some(date)) {
+            self.start.set(None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::habit::{Count, Habit, TrackEvent};
+
+    #[test]
+    fn metadata_is_saved_alongside_the_habit() {
+        let mut h = Count::new("walk", 2, true);
+        h.set_period(Period::Week);
+        h.set_note(NaiveDate::from_ymd(2021, 3, 1), Some("rain".into()));
+        let json = serde_json::to_value(&h).unwrap();
+        assert_eq!(json["period"], "week");
+        assert_eq!(json["notes"]["2021-03-01"], "rain");
+        assert_eq!(json["auto"], true);
+
+        let h: Count = serde_json::from_value(json).unwrap();
+        assert_eq!(Habit::period(&h), Period::Week);
+        assert!(Habit::is_auto(&h));
+        assert_eq!(
+            Habit::noted_dates(&h),
+            vec![NaiveDate::from_ymd(2021, 3, 1)]
+        );
+    }
+
+    #[test]
+    fn start_follows_the_first_tracked_day() {
+        let mut h = Count::new("walk", 2, false);
+        let created = Habit::created(&h).unwrap();
+        let earlier = created - chrono::Duration::days(10);
+        h.set_value(earlier, "1");
+        h.set_value(earlier.succ(), "1");
+        assert_eq!(Habit::created(&h), Some(earlier));
+
+        Habit::remove_entry(&mut h, earlier);
+        assert_eq!(Habit::created(&h), Some(earlier.succ()));
+        h.modify(earlier.succ(), TrackEvent::Decrement);
+        h.modify(earlier.succ(), TrackEvent::Decrement);
+        assert_eq!(Habit::created(&h), Some(created));
+    }
+}
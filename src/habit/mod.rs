@@ -12,8 +12,16 @@ pub use bit::Bit;
 mod float;
 pub use float::Float;
 
+mod addiction;
+pub use addiction::Addiction;
+
+mod metadata;
+
 mod prelude;
-pub use prelude::{TrackEvent, ViewMode};
+pub use prelude::{Period, Schedule, TrackEvent, ViewMode};
+
+mod stats;
+pub use stats::{completions, progress, Stats};
 
 use crate::app::Cursor;
 
@@ -1,8 +1,13 @@
+use chrono::{Datelike, Duration, Local, NaiveDate, Weekday};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::default;
 use std::fmt;
 
-#[derive(Debug, PartialEq)]
+use crate::app::add_months;
+use crate::CONFIGURATION;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TrackEvent {
     Increment,
     Decrement,
@@ -33,6 +38,146 @@ impl fmt::Display for ViewMode {
     }
 }
 
+// The span of time a goal is to be reached in. Weeks start on the
+// configured first day of the week.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl Period {
+    // the first day of the period that `date` falls in
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => CONFIGURATION.week_start.week_of(date),
+            Period::Month => date.with_day(1).unwrap(),
+        }
+    }
+    // the first day of the period following the one `date` falls in
+    pub fn next(&self, date: NaiveDate) -> NaiveDate {
+        let start = self.start(date);
+        match self {
+            Period::Day => start.succ(),
+            Period::Week => start + Duration::weeks(1),
+            Period::Month => add_months(start, 1),
+        }
+    }
+    // every day of the period that `date` falls in
+    pub fn days(&self, date: NaiveDate) -> Vec<NaiveDate> {
+        let (start, next) = (self.start(date), self.next(date));
+        let len = next.signed_duration_since(start).num_days();
+        return (0..len).map(|i| start + Duration::days(i)).collect();
+    }
+}
+
+// The days a habit is to be done on. Days off the schedule are
+// neither reached nor missed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Schedule {
+    #[default]
+    Daily,
+    Weekdays(Vec<Weekday>),
+    Every(u32, NaiveDate), // every n days, counted from a date
+}
+
+impl Schedule {
+    pub fn is_due(&self, date: NaiveDate) -> bool {
+        match self {
+            Schedule::Daily => true,
+            Schedule::Weekdays(days) => days.contains(&date.weekday()),
+            Schedule::Every(n, from) => {
+                let since = date.signed_duration_since(*from).num_days();
+                since.rem_euclid(*n as i64) == 0
+            }
+        }
+    }
+}
+
+// the schedule expression that parses back into this schedule
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Daily => write!(f, "daily"),
+            Schedule::Weekdays(days) => {
+                let days = days
+                    .iter()
+                    .map(|d| d.to_string().to_lowercase())
+                    .collect::<Vec<_>>();
+                write!(f, "{}", days.join(","))
+            }
+            Schedule::Every(n, _) => write!(f, "every {} days", n),
+        }
+    }
+}
+
+// The goal in force on `date`. `replaced` holds every earlier goal
+// of a habit, keyed by the date it was replaced on, and `current`
+// is in force from the last of those dates onwards.
+pub fn goal_in_force<T: Copy>(replaced: &BTreeMap<NaiveDate, T>, current: T, date: NaiveDate) -> T {
+    return replaced
+        .range(date.succ()..)
+        .next()
+        .map(|(_, g)| *g)
+        .unwrap_or(current);
+}
+
+// Record that the goal `before` is replaced by `after` as of today,
+// days before today are still judged against `before`.
+pub fn replace_goal<T: Copy + PartialEq>(
+    replaced: &mut BTreeMap<NaiveDate, T>,
+    before: T,
+    after: T,
+) {
+    let today = Local::now().naive_local().date();
+    // if the goal was already changed today, it is the goal from
+    // before today that is kept
+    let before = *replaced.entry(today).or_insert(before);
+    // changing back to it leaves nothing to remember
+    if before == after {
+        replaced.remove(&today);
+    }
+}
+
 pub fn default_auto() -> bool {
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn goals_in_force() {
+        let d = |day| NaiveDate::from_ymd(2021, 3, day);
+        let replaced = [(d(10), 4), (d(20), 6)].iter().copied().collect();
+        assert_eq!(goal_in_force(&replaced, 8, d(1)), 4);
+        assert_eq!(goal_in_force(&replaced, 8, d(9)), 4);
+        assert_eq!(goal_in_force(&replaced, 8, d(10)), 6);
+        assert_eq!(goal_in_force(&replaced, 8, d(19)), 6);
+        assert_eq!(goal_in_force(&replaced, 8, d(20)), 8);
+        assert_eq!(goal_in_force(&BTreeMap::new(), 8, d(1)), 8);
+    }
+
+    #[test]
+    fn replaced_goals() {
+        let today = Local::now().naive_local().date();
+        let mut replaced = BTreeMap::new();
+        replace_goal(&mut replaced, 3, 5);
+        assert_eq!(goal_in_force(&replaced, 5, today.pred()), 3);
+        assert_eq!(goal_in_force(&replaced, 5, today), 5);
+
+        // a second change on the same day keeps the goal from before
+        replace_goal(&mut replaced, 5, 7);
+        assert_eq!(goal_in_force(&replaced, 7, today.pred()), 3);
+
+        // and changing back to it forgets the change
+        replace_goal(&mut replaced, 7, 3);
+        assert!(replaced.is_empty());
+    }
+}
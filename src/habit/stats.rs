@@ -0,0 +1,276 @@
+use chrono::{Datelike, Local, NaiveDate, Weekday};
+
+use crate::habit::{Habit, Period};
+use crate::CONFIGURATION;
+
+// goal completions over `days`, as (completed, total). Every goal
+// period that `days` touch counts once, for daily goals that is
+// every day. Days off the schedule are left out.
+pub fn completions<T: Habit + ?Sized>(habit: &T, days: &[NaiveDate]) -> (u32, u32) {
+    periods(habit, days)
+        .iter()
+        .fold((0, 0), |(completed, total), &d| {
+            // every period counts against the goal it is judged by
+            let goal = habit.goal_units_on(d);
+            (
+                completed + goal - habit.remaining(d).min(goal),
+                total + goal,
+            )
+        })
+}
+
+// as `completions`, leaving out days after today, they cannot be
+// completed yet
+pub fn progress<T: Habit + ?Sized>(habit: &T, days: &[NaiveDate]) -> (u32, u32) {
+    let today = Local::now().naive_local().date();
+    let days = days
+        .iter()
+        .copied()
+        .filter(|&d| d <= today)
+        .collect::<Vec<_>>();
+    return completions(habit, &days);
+}
+
+// a day of every active goal period that `days` touch, the first
+// active one, as inactive days have nothing remaining
+fn periods<T: Habit + ?Sized>(habit: &T, days: &[NaiveDate]) -> Vec<NaiveDate> {
+    let mut active = days
+        .iter()
+        .copied()
+        .filter(|&d| habit.is_active(d))
+        .collect::<Vec<_>>();
+    active.dedup_by_key(|d| habit.period_on(*d).start(*d));
+    return active;
+}
+
+// the number of goal periods touched by `days` in which the goal
+// was reached
+fn reached<T: Habit + ?Sized>(habit: &T, days: &[NaiveDate]) -> u32 {
+    return periods(habit, days)
+        .into_iter()
+        .filter(|&d| habit.reached_goal(d))
+        .count() as u32;
+}
+
+fn percentage((completed, total): (u32, u32)) -> Option<u32> {
+    if total == 0 {
+        return None;
+    }
+    return Some(completed * 100 / total);
+}
+
+// Reached goals and completion rate of a single month
+pub struct MonthStats {
+    pub year: i32,
+    pub month: u32,
+    pub completions: u32,
+    pub rate: Option<u32>,
+}
+
+// Aggregates over the history of a habit, from the day it was
+// created up to today
+pub struct Stats {
+    pub since: Option<NaiveDate>,
+    pub completions: u32,               // goal periods reached
+    pub rates: Vec<(u32, Option<u32>)>, // days, percentage of the goal met
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    pub average: Option<f64>, // mean tracked value of count and float habits
+    pub best_weekday: Option<Weekday>,
+    pub months: Vec<MonthStats>, // newest first
+}
+
+impl Stats {
+    pub fn of<T: Habit + ?Sized>(habit: &T) -> Self {
+        let today = Local::now().naive_local().date();
+        let since = habit.created().filter(|&d| d <= today);
+        let mut days = Vec::new();
+        if let Some(first) = since {
+            let mut day = first;
+            while day <= today {
+                days.push(day);
+                day = day.succ();
+            }
+        }
+
+        let rates = [7, 30, 365]
+            .iter()
+            .map(|&n| {
+                let window = &days[days.len().saturating_sub(n as usize)..];
+                (n, percentage(progress(habit, window)))
+            })
+            .collect();
+
+        let values = days
+            .iter()
+            .filter_map(|&d| habit.get_by_date(d))
+            .filter_map(|v| habit.as_number(v))
+            .collect::<Vec<_>>();
+        let average = if values.is_empty() {
+            None
+        } else {
+            Some(values.iter().sum::<f64>() / values.len() as f64)
+        };
+
+        let mut months: Vec<MonthStats> = Vec::new();
+        let mut start = 0;
+        while start < days.len() {
+            let (year, month) = (days[start].year(), days[start].month());
+            let len = days[start..]
+                .iter()
+                .take_while(|d| d.month() == month)
+                .count();
+            let month_days = &days[start..start + len];
+            months.push(MonthStats {
+                year,
+                month,
+                completions: reached(habit, month_days),
+                rate: percentage(progress(habit, month_days)),
+            });
+            start += len;
+        }
+        months.reverse();
+
+        return Stats {
+            since,
+            completions: reached(habit, &days),
+            rates,
+            current_streak: habit.current_streak(),
+            longest_streak: habit.longest_streak(),
+            average,
+            best_weekday: best_weekday(habit, &days),
+            months,
+        };
+    }
+}
+
+// The weekday with the highest share of active days on which the
+// goal was reached. Longer goals are reached on every day of the
+// period, so for those it is the weekday the habit is most often
+// tracked on.
+fn best_weekday<T: Habit + ?Sized>(habit: &T, days: &[NaiveDate]) -> Option<Weekday> {
+    // (done, active) for every weekday, from the first day of the week
+    let mut counts = [(0, 0); 7];
+    let first = CONFIGURATION.week_start.week_of(days.first().copied()?);
+    for &d in days.iter().filter(|&&d| habit.is_active(d)) {
+        let done = match habit.period_on(d) {
+            Period::Day => habit.reached_goal(d),
+            _ => habit.get_by_date(d).is_some(),
+        };
+        let idx = d.signed_duration_since(first).num_days().rem_euclid(7) as usize;
+        counts[idx].0 += done as u32;
+        counts[idx].1 += 1;
+    }
+    // ties go to the earlier day of the week
+    let best = counts
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, &(done, _))| done > 0)
+        .map(|(idx, &(done, active))| (idx, done as f64 / active as f64))
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())?
+        .0;
+    let mut day = first.weekday();
+    for _ in 0..best {
+        day = day.succ();
+    }
+    return Some(day);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::habit::{Count, Schedule};
+    use chrono::Duration;
+
+    // every day of the first full week after today, so all of them
+    // are on or after the day the habit was created
+    fn next_week() -> Vec<NaiveDate> {
+        let today = Local::now().naive_local().date();
+        let start = CONFIGURATION.week_start.week_of(today) + Duration::weeks(1);
+        return (0..7).map(|i| start + Duration::days(i)).collect();
+    }
+
+    #[test]
+    fn weekly_goal_on_weekends() {
+        let mut h = Count::new("climb", 2, false);
+        Habit::set_period(&mut h, Period::Week);
+        Habit::set_schedule(&mut h, Schedule::Weekdays(vec![Weekday::Sat, Weekday::Sun]));
+        let week = next_week();
+        assert_eq!(completions(&h, &week), (0, 2));
+
+        let saturday = *week.iter().find(|d| d.weekday() == Weekday::Sat).unwrap();
+        assert!(Habit::set_value(&mut h, saturday, "1"));
+        assert_eq!(completions(&h, &week), (1, 2));
+        assert_eq!(completions(&h, &[saturday]), (1, 2));
+        assert_eq!(reached(&h, &week), 0);
+    }
+
+    // every day of the week `n` weeks before the current one
+    fn weeks_ago(n: i64) -> Vec<NaiveDate> {
+        let today = Local::now().naive_local().date();
+        let start = CONFIGURATION.week_start.week_of(today) - Duration::weeks(n);
+        return (0..7).map(|i| start + Duration::days(i)).collect();
+    }
+
+    // a count habit created four weeks ago, with `history` spliced
+    // into its fields
+    fn count_with(goal: u32, period: &str, history: &str) -> Count {
+        let created = weeks_ago(4)[0];
+        let json = format!(
+            r#"{{"name": "pushups", "stats": {{}}, "goal": {}, "period": "{}", "created": "{}", {}}}"#,
+            goal, period, created, history
+        );
+        return serde_json::from_str(&json).unwrap();
+    }
+
+    #[test]
+    fn week_percentage_uses_the_goal_in_force() {
+        // the goal was 4 until the week after the tracked one
+        let week = weeks_ago(2);
+        let replaced = weeks_ago(1)[0];
+        let mut h = count_with(
+            2,
+            "day",
+            &format!(r#""goal_history": {{"{}": 4}}"#, replaced),
+        );
+        for &d in &week {
+            assert!(Habit::set_value(&mut h, d, "2"));
+        }
+        assert_eq!(completions(&h, &week), (14, 28));
+        assert_eq!(percentage(completions(&h, &week)), Some(50));
+        assert_eq!(reached(&h, &week), 0);
+    }
+
+    #[test]
+    fn changing_the_period_keeps_past_periods() {
+        // daily until the week after the tracked one, weekly since
+        let week = weeks_ago(2);
+        let replaced = weeks_ago(1)[0];
+        let mut h = count_with(
+            3,
+            "week",
+            &format!(r#""period_history": {{"{}": "day"}}"#, replaced),
+        );
+        assert!(Habit::set_value(&mut h, week[0], "3"));
+        assert_eq!(completions(&h, &week), (3, 21));
+        assert_eq!(reached(&h, &week), 1);
+
+        // the same entry a week later reaches the weekly goal
+        let later = weeks_ago(1);
+        assert!(Habit::set_value(&mut h, later[0], "3"));
+        assert_eq!(completions(&h, &later), (3, 3));
+        assert_eq!(reached(&h, &later), 1);
+    }
+
+    #[test]
+    fn set_period_applies_from_today() {
+        let today = Local::now().naive_local().date();
+        let mut h = count_with(1, "day", r#""goal_history": {}"#);
+        assert!(Habit::set_value(&mut h, today.pred(), "1"));
+        Habit::set_period(&mut h, Period::Week);
+        assert_eq!(Habit::period_on(&h, today.pred()), Period::Day);
+        assert_eq!(Habit::period_on(&h, today), Period::Week);
+        assert!(Habit::reached_goal(&h, today.pred()));
+    }
+}
@@ -1,32 +1,249 @@
-use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+use chrono::{Local, NaiveDate};
 use cursive::direction::Direction;
 use cursive::event::{Event, EventResult};
 use cursive::view::CannotFocus;
 use cursive::{Printer, Vec2};
 use typetag;
 
-use crate::command::GoalKind;
-use crate::habit::{Bit, Count, Float, InnerData, TrackEvent};
+use crate::command::{Goal, GoalKind};
+use crate::habit::metadata::Metadata;
+use crate::habit::prelude::{goal_in_force, replace_goal};
+use crate::habit::{Addiction, Bit, Count, Float, InnerData, Period, Schedule, Stats, TrackEvent};
 use crate::views::ShadowView;
 
 pub trait Habit {
-    type HabitType;
+    type HabitType: Copy;
 
     fn get_by_date(&self, date: NaiveDate) -> Option<&Self::HabitType>;
+    fn tracked_dates(&self) -> Vec<NaiveDate>;
     fn goal(&self) -> u32;
+    // the goal in force on `date` as a number, like `goal`
+    fn goal_units_on(&self, date: NaiveDate) -> u32;
     fn insert_entry(&mut self, date: NaiveDate, val: Self::HabitType);
+    fn remove_entry(&mut self, date: NaiveDate);
+    fn parse_value(&self, value: &str) -> Option<Self::HabitType>;
+    fn format_value(&self, value: &Self::HabitType) -> String;
+    // the value as a number, for habits whose values can be averaged
+    fn as_number(&self, value: &Self::HabitType) -> Option<f64>;
     fn modify(&mut self, date: NaiveDate, event: TrackEvent);
     fn name(&self) -> String;
     fn reached_goal(&self, date: NaiveDate) -> bool;
     fn remaining(&self, date: NaiveDate) -> u32;
     fn set_goal(&mut self, goal: Self::HabitType);
+    fn current_goal(&self) -> Self::HabitType;
+    // earlier goals, keyed by the date they were replaced on
+    fn goal_history(&self) -> &BTreeMap<NaiveDate, Self::HabitType>;
+    fn goal_from_kind(&self, goal: GoalKind) -> Option<Self::HabitType>;
     fn set_name(&mut self, name: impl AsRef<str>);
-    fn kind(&self) -> GoalKind;
+    // the kind of habit, with `goal` as its goal
+    fn goal_kind(&self, goal: Self::HabitType) -> GoalKind;
 
-    fn inner_data_ref(&self) -> &InnerData;
-    fn inner_data_mut_ref(&mut self) -> &mut InnerData;
+    fn meta(&self) -> &Metadata;
+    fn meta_mut(&mut self) -> &mut Metadata;
 
-    fn is_auto(&self) -> bool;
+    fn inner_data_ref(&self) -> &InnerData {
+        &self.meta().inner_data
+    }
+    fn inner_data_mut_ref(&mut self) -> &mut InnerData {
+        &mut self.meta_mut().inner_data
+    }
+    fn is_auto(&self) -> bool {
+        self.meta().auto
+    }
+    fn is_archived(&self) -> bool {
+        self.meta().archived
+    }
+    fn set_archived(&mut self, archived: bool) {
+        self.meta_mut().archived = archived;
+    }
+    fn created(&self) -> Option<NaiveDate> {
+        // tracking a day before the habit was created moves its start
+        // back to that day, habits saved before creation dates were
+        // recorded start on their first tracked day
+        if let Some(start) = self.meta().start.get() {
+            return start;
+        }
+        let first = self.tracked_dates().first().copied();
+        let start = match (self.meta().created, first) {
+            (Some(c), Some(f)) => Some(c.min(f)),
+            (c, f) => c.or(f),
+        };
+        self.meta().start.set(Some(start));
+        return start;
+    }
+    fn period(&self) -> Period {
+        self.meta().period
+    }
+    // the period in force on `date`, earlier periods are kept when
+    // it is changed so past days are not judged again
+    fn period_on(&self, date: NaiveDate) -> Period {
+        let meta = self.meta();
+        return goal_in_force(&meta.period_history, meta.period, date);
+    }
+    fn set_period(&mut self, period: Period) {
+        let meta = self.meta_mut();
+        replace_goal(&mut meta.period_history, meta.period, period);
+        meta.period = period;
+    }
+    fn schedule(&self) -> &Schedule {
+        &self.meta().schedule
+    }
+    fn set_schedule(&mut self, schedule: Schedule) {
+        self.meta_mut().schedule = schedule;
+    }
+    fn is_excused(&self, date: NaiveDate) -> bool {
+        self.meta().excused.contains(&date)
+    }
+    fn set_excused(&mut self, date: NaiveDate, excused: bool) {
+        let excused_days = &mut self.meta_mut().excused;
+        if excused {
+            excused_days.insert(date);
+        } else {
+            excused_days.remove(&date);
+        }
+    }
+    fn note(&self, date: NaiveDate) -> Option<&String> {
+        self.meta().notes.get(&date)
+    }
+    fn set_note(&mut self, date: NaiveDate, note: Option<String>) {
+        let notes = &mut self.meta_mut().notes;
+        match note {
+            Some(n) => notes.insert(date, n),
+            None => notes.remove(&date),
+        };
+    }
+    fn noted_dates(&self) -> Vec<NaiveDate> {
+        self.meta().notes.keys().copied().collect()
+    }
+
+    fn goal_on(&self, date: NaiveDate) -> Self::HabitType {
+        // a period is judged against the goal in force on its last day
+        let last = self.period_on(date).next(date).pred();
+        return goal_in_force(self.goal_history(), self.current_goal(), last);
+    }
+
+    fn kind(&self) -> GoalKind {
+        return self.goal_kind(self.current_goal());
+    }
+    // the kind of habit, with the goal in force on `date`
+    fn kind_on(&self, date: NaiveDate) -> GoalKind {
+        return self.goal_kind(self.goal_on(date));
+    }
+
+    // days before the habit was created, days off the schedule and
+    // excused days are neither reached nor missed, they are left out
+    // of progress and streaks
+    fn is_active(&self, date: NaiveDate) -> bool {
+        let started = match self.created() {
+            Some(c) => date >= c,
+            None => true,
+        };
+        return started && self.schedule().is_due(date) && !self.is_excused(date);
+    }
+
+    // whether the goal period that `date` falls in has an active day
+    fn is_active_period(&self, date: NaiveDate) -> bool {
+        return self
+            .period_on(date)
+            .days(date)
+            .into_iter()
+            .any(|d| self.is_active(d));
+    }
+
+    // values tracked within the goal period that `date` falls in
+    fn period_values(&self, date: NaiveDate) -> Vec<&Self::HabitType> {
+        return self
+            .period_on(date)
+            .days(date)
+            .into_iter()
+            .filter_map(|d| self.get_by_date(d))
+            .collect();
+    }
+
+    // set the entry for `date` outright, returns false if `value`
+    // is not valid for this kind of habit
+    fn set_value(&mut self, date: NaiveDate, value: &str) -> bool {
+        match self.parse_value(value) {
+            Some(v) => {
+                self.insert_entry(date, v);
+                return true;
+            }
+            None => return false,
+        }
+    }
+
+    // returns false if `goal` does not fit this kind of habit
+    fn set_goal_kind(&mut self, goal: GoalKind) -> bool {
+        match self.goal_from_kind(goal) {
+            Some(g) => {
+                self.set_goal(g);
+                return true;
+            }
+            None => return false,
+        }
+    }
+
+    // the last day of every goal period from the creation of the
+    // habit up to today, newest first. Periods are walked back from
+    // today, as the period in force on the last day of a period
+    // decides how long it is.
+    fn period_ends(&self) -> Vec<NaiveDate> {
+        let first = match self.created() {
+            Some(d) => d,
+            None => return Vec::new(),
+        };
+        let mut ends = Vec::new();
+        let mut day = Local::now().naive_local().date();
+        while day >= first {
+            ends.push(day);
+            day = self.period_on(day).start(day).pred();
+        }
+        return ends;
+    }
+
+    // consecutive periods (days, for daily goals) from the creation
+    // of the habit up to today in which the goal was reached, the
+    // current period only breaks the streak once it is over.
+    // Inactive periods are skipped.
+    fn current_streak(&self) -> u32 {
+        let mut ends = self.period_ends();
+        if let Some(&today) = ends.first() {
+            if !self.reached_goal(today) {
+                ends.remove(0);
+            }
+        }
+        let mut streak = 0;
+        for day in ends {
+            if self.is_active_period(day) {
+                if !self.reached_goal(day) {
+                    break;
+                }
+                streak += 1;
+            }
+        }
+        return streak;
+    }
+
+    fn stats(&self) -> Stats {
+        return Stats::of(self);
+    }
+
+    fn longest_streak(&self) -> u32 {
+        let (mut longest, mut streak) = (0, 0);
+        for day in self.period_ends() {
+            if self.is_active_period(day) {
+                if self.reached_goal(day) {
+                    streak += 1;
+                    longest = longest.max(streak);
+                } else {
+                    streak = 0;
+                }
+            }
+        }
+        return longest;
+    }
 }
 
 #[typetag::serde(tag = "type")]
@@ -34,10 +251,19 @@ pub trait HabitWrapper: erased_serde::Serialize {
     fn draw(&self, printer: &Printer);
     fn goal(&self) -> u32;
     fn kind(&self) -> GoalKind;
+    fn kind_on(&self, date: NaiveDate) -> GoalKind;
     fn modify(&mut self, date: NaiveDate, event: TrackEvent);
+    fn set_value(&mut self, date: NaiveDate, value: &str) -> bool;
+    fn normalize_value(&self, value: &str) -> Option<String>;
+    fn remove_entry(&mut self, date: NaiveDate);
     fn name(&self) -> String;
+    fn set_name(&mut self, name: &str);
+    fn set_goal_kind(&mut self, goal: GoalKind) -> bool;
     fn on_event(&mut self, event: Event) -> EventResult;
+    fn reached_goal(&self, date: NaiveDate) -> bool;
     fn remaining(&self, date: NaiveDate) -> u32;
+    fn tracked_dates(&self) -> Vec<NaiveDate>;
+    fn value(&self, date: NaiveDate) -> Option<String>;
     fn required_size(&mut self, _: Vec2) -> Vec2;
     fn take_focus(&mut self, _: Direction) -> Result<EventResult, CannotFocus>;
 
@@ -45,6 +271,39 @@ pub trait HabitWrapper: erased_serde::Serialize {
     fn inner_data_mut_ref(&mut self) -> &mut InnerData;
 
     fn is_auto(&self) -> bool;
+    fn is_archived(&self) -> bool;
+    fn set_archived(&mut self, archived: bool);
+    fn period(&self) -> Period;
+    fn period_on(&self, date: NaiveDate) -> Period;
+    fn set_period(&mut self, period: Period);
+    fn schedule(&self) -> Schedule;
+    fn set_schedule(&mut self, schedule: Schedule);
+    fn is_excused(&self, date: NaiveDate) -> bool;
+    fn set_excused(&mut self, date: NaiveDate, excused: bool);
+    fn is_active(&self, date: NaiveDate) -> bool;
+    fn note(&self, date: NaiveDate) -> Option<String>;
+    fn set_note(&mut self, date: NaiveDate, note: Option<String>);
+    fn noted_dates(&self) -> Vec<NaiveDate>;
+
+    fn current_streak(&self) -> u32;
+    fn longest_streak(&self) -> u32;
+    fn stats(&self) -> Stats;
+
+    // the goal expression of the habit, as in `3/w`
+    fn goal_expr(&self) -> Goal {
+        return Goal {
+            kind: self.kind(),
+            period: self.period(),
+        };
+    }
+
+    // the goal expression in force on `date`
+    fn goal_expr_on(&self, date: NaiveDate) -> Goal {
+        return Goal {
+            kind: self.kind_on(date),
+            period: self.period_on(date),
+        };
+    }
 }
 
 macro_rules! auto_habit_impl {
@@ -66,21 +325,48 @@ macro_rules! auto_habit_impl {
             }
 
             // Habit
+            fn reached_goal(&self, date: NaiveDate) -> bool {
+                Habit::reached_goal(self, date)
+            }
             fn remaining(&self, date: NaiveDate) -> u32 {
                 Habit::remaining(self, date)
             }
+            fn tracked_dates(&self) -> Vec<NaiveDate> {
+                Habit::tracked_dates(self)
+            }
+            fn value(&self, date: NaiveDate) -> Option<String> {
+                Habit::get_by_date(self, date).map(|v| Habit::format_value(self, v))
+            }
             fn goal(&self) -> u32 {
                 Habit::goal(self)
             }
             fn kind(&self) -> GoalKind {
                 Habit::kind(self)
             }
+            fn kind_on(&self, date: NaiveDate) -> GoalKind {
+                Habit::kind_on(self, date)
+            }
             fn modify(&mut self, date: NaiveDate, event: TrackEvent) {
                 Habit::modify(self, date, event);
             }
+            fn set_value(&mut self, date: NaiveDate, value: &str) -> bool {
+                Habit::set_value(self, date, value)
+            }
+            fn remove_entry(&mut self, date: NaiveDate) {
+                Habit::remove_entry(self, date)
+            }
+            fn normalize_value(&self, value: &str) -> Option<String> {
+                Habit::parse_value(self, value).map(|v| Habit::format_value(self, &v))
+            }
             fn name(&self) -> String {
                 Habit::name(self)
             }
+            fn set_name(&mut self, name: &str) {
+                Habit::set_name(self, name)
+            }
+            fn set_goal_kind(&mut self, goal: GoalKind) -> bool {
+                Habit::set_goal_kind(self, goal)
+            }
             fn inner_data_ref(&self) -> &InnerData {
                 Habit::inner_data_ref(self)
             }
@@ -90,6 +376,54 @@ macro_rules! auto_habit_impl {
             fn is_auto(&self) -> bool {
                 Habit::is_auto(self)
             }
+            fn is_archived(&self) -> bool {
+                Habit::is_archived(self)
+            }
+            fn set_archived(&mut self, archived: bool) {
+                Habit::set_archived(self, archived)
+            }
+            fn period(&self) -> Period {
+                Habit::period(self)
+            }
+            fn period_on(&self, date: NaiveDate) -> Period {
+                Habit::period_on(self, date)
+            }
+            fn set_period(&mut self, period: Period) {
+                Habit::set_period(self, period)
+            }
+            fn schedule(&self) -> Schedule {
+                Habit::schedule(self).clone()
+            }
+            fn set_schedule(&mut self, schedule: Schedule) {
+                Habit::set_schedule(self, schedule)
+            }
+            fn is_excused(&self, date: NaiveDate) -> bool {
+                Habit::is_excused(self, date)
+            }
+            fn set_excused(&mut self, date: NaiveDate, excused: bool) {
+                Habit::set_excused(self, date, excused)
+            }
+            fn is_active(&self, date: NaiveDate) -> bool {
+                Habit::is_active(self, date)
+            }
+            fn note(&self, date: NaiveDate) -> Option<String> {
+                Habit::note(self, date).cloned()
+            }
+            fn set_note(&mut self, date: NaiveDate, note: Option<String>) {
+                Habit::set_note(self, date, note)
+            }
+            fn noted_dates(&self) -> Vec<NaiveDate> {
+                Habit::noted_dates(self)
+            }
+            fn current_streak(&self) -> u32 {
+                Habit::current_streak(self)
+            }
+            fn longest_streak(&self) -> u32 {
+                Habit::longest_streak(self)
+            }
+            fn stats(&self) -> Stats {
+                Habit::stats(self)
+            }
         }
     };
 }
@@ -102,4 +436,69 @@ macro_rules! generate_implementations {
     );
 }
 
-generate_implementations!(Count, Bit, Float);
+generate_implementations!(Count, Bit, Float, Addiction);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn days_ago(n: i64) -> NaiveDate {
+        Local::now().naive_local().date() - Duration::days(n)
+    }
+
+    // set `value` on each of the given days back from today
+    fn track<T: Habit>(habit: &mut T, days: &[i64], value: &str) {
+        for &n in days {
+            assert!(habit.set_value(days_ago(n), value));
+        }
+    }
+
+    #[test]
+    fn count_streaks() {
+        let mut h = Count::new("pushups", 2, false);
+        track(&mut h, &[6, 5, 4, 2, 1], "2");
+        track(&mut h, &[3], "1");
+        assert_eq!(Habit::current_streak(&h), 2);
+        assert_eq!(Habit::longest_streak(&h), 3);
+    }
+
+    #[test]
+    fn bit_streaks() {
+        let mut h = Bit::new("walk", false);
+        track(&mut h, &[4, 2, 1, 0], "1");
+        track(&mut h, &[3], "0");
+        assert_eq!(Habit::current_streak(&h), 3);
+        assert_eq!(Habit::longest_streak(&h), 3);
+    }
+
+    #[test]
+    fn float_streaks() {
+        let mut h = Float::new("run", 25, 1, false);
+        track(&mut h, &[3, 2], "2.5");
+        track(&mut h, &[1], "2.4");
+        assert_eq!(Habit::current_streak(&h), 0);
+        assert_eq!(Habit::longest_streak(&h), 2);
+    }
+
+    #[test]
+    fn addiction_streaks_count_clean_days_since_creation() {
+        let json = format!(
+            r#"{{"name": "smoke", "stats": {{}}, "limit": 2, "created": "{}"}}"#,
+            days_ago(5)
+        );
+        let mut h: Addiction = serde_json::from_str(&json).unwrap();
+        assert_eq!(Habit::current_streak(&h), 6);
+        assert_eq!(Habit::longest_streak(&h), 6);
+        track(&mut h, &[2], "3");
+        assert_eq!(Habit::current_streak(&h), 2);
+        assert_eq!(Habit::longest_streak(&h), 3);
+    }
+
+    #[test]
+    fn untracked_habits_have_no_streak() {
+        let h = Count::new("pushups", 2, false);
+        assert_eq!(Habit::current_streak(&h), 0);
+        assert_eq!(Habit::longest_streak(&h), 0);
+    }
+}
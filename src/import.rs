@@ -0,0 +1,336 @@
+use std::fmt;
+use std::io::{self, Read};
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+
+use crate::command::CommandLineError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    Csv,
+    Loop,
+}
+
+impl FromStr for ImportFormat {
+    type Err = CommandLineError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "csv" => Ok(ImportFormat::Csv),
+            "loop" => Ok(ImportFormat::Loop),
+            _ => Err(CommandLineError::UnknownFormat(s.into())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    Parse(usize, String), // line, reason
+}
+
+impl std::error::Error for ImportError {}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "{}", e),
+            ImportError::Parse(line, reason) => write!(f, "line {}: {}", line, reason),
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+// A single value to be imported into a habit
+#[derive(Debug, PartialEq)]
+pub struct Record {
+    pub line: usize,
+    pub date: NaiveDate,
+    pub habit: String,
+    pub value: String,
+    pub goal: Option<String>, // goal expression for new habits
+}
+
+// An imported value that differs from the one already tracked
+#[derive(Debug)]
+pub struct Conflict {
+    pub habit: String,
+    pub date: NaiveDate,
+    pub existing: String,
+    pub imported: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "`{}` on {} is {}, not overwriting it with {}",
+            self.habit, self.date, self.existing, self.imported
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub created: Vec<String>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Imported {} entries", self.imported)?;
+        if !self.created.is_empty() {
+            write!(f, ", added {} habit(s)", self.created.len())?;
+        }
+        if !self.conflicts.is_empty() {
+            write!(f, ", skipped {} conflict(s)", self.conflicts.len())?;
+        }
+        Ok(())
+    }
+}
+
+// split a line of CSV, fields may be quoted and quotes within
+// quoted fields are doubled
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().unwrap();
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    return fields.into_iter().map(|f| f.trim().to_string()).collect();
+}
+
+// non-empty rows of the input, split into fields, along with the
+// line numbers they start on. A quoted field may span several
+// lines, as multi-line notes do in `:export csv`.
+fn csv_lines(input: &mut impl Read) -> Result<Vec<(usize, Vec<String>)>, ImportError> {
+    let mut contents = String::new();
+    input.read_to_string(&mut contents)?;
+    let mut rows = Vec::new();
+    let mut open: Option<(usize, String)> = None;
+    for (idx, l) in contents.lines().enumerate() {
+        let (line, row) = match open.take() {
+            Some((line, row)) => (line, row + "\n" + l),
+            None => (idx + 1, l.to_string()),
+        };
+        // an odd number of quotes leaves a quoted field open
+        if row.matches('"').count() % 2 == 1 {
+            open = Some((line, row));
+        } else if !row.trim().is_empty() {
+            rows.push((line, split_csv_line(&row)));
+        }
+    }
+    // a quote that is never closed runs to the end of the input
+    if let Some((line, row)) = open {
+        rows.push((line, split_csv_line(&row)));
+    }
+    return Ok(rows);
+}
+
+fn parse_date(line: usize, s: &str) -> Result<NaiveDate, ImportError> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| ImportError::Parse(line, format!("invalid date `{}`", s)))
+}
+
+pub fn read_records(
+    format: ImportFormat,
+    input: &mut impl Read,
+) -> Result<Vec<Record>, ImportError> {
+    let lines = csv_lines(input)?;
+    match format {
+        ImportFormat::Csv => return read_csv(lines),
+        ImportFormat::Loop => return read_loop(lines),
+    }
+}
+
+// rows of `date,habit,value[,goal]`. A header row may name the
+// columns in any order, which also allows files written by
+// `:export csv` to be read back.
+fn read_csv(lines: Vec<(usize, Vec<String>)>) -> Result<Vec<Record>, ImportError> {
+    let mut lines = lines.into_iter().peekable();
+    let column =
+        |header: &[String], name: &str| header.iter().position(|h| h.to_lowercase() == name);
+    let (date, habit, value, goal) = match lines.peek() {
+        Some((line, header)) if column(header, "date").is_some() => {
+            let line = *line;
+            let required = |name| {
+                column(header, name)
+                    .ok_or_else(|| ImportError::Parse(line, format!("missing `{}` column", name)))
+            };
+            let columns = (
+                required("date")?,
+                required("habit")?,
+                required("value")?,
+                column(header, "goal"),
+            );
+            lines.next();
+            columns
+        }
+        _ => (0, 1, 2, Some(3)),
+    };
+
+    let mut records = Vec::new();
+    for (line, fields) in lines {
+        let field = |idx: usize| fields.get(idx).map(String::as_str).unwrap_or_default();
+        if field(value).is_empty() {
+            continue;
+        }
+        if field(habit).is_empty() {
+            return Err(ImportError::Parse(line, "missing habit name".into()));
+        }
+        records.push(Record {
+            line,
+            date: parse_date(line, field(date))?,
+            habit: field(habit).into(),
+            value: field(value).into(),
+            goal: goal.map(field).filter(|g| !g.is_empty()).map(String::from),
+        });
+    }
+    return Ok(records);
+}
+
+// Loop Habit Tracker exports `Checkmarks.csv`, with a column per
+// habit and a row per day. Yes/no habits are written as 2 (checked),
+// 1 (implied by the habit's frequency), 0 (unchecked), -1 (unknown)
+// or 3 (skipped), only days that were checked are imported. Columns
+// with other values are numerical habits, and are imported as is.
+fn read_loop(lines: Vec<(usize, Vec<String>)>) -> Result<Vec<Record>, ImportError> {
+    let mut lines = lines.into_iter();
+    let habits = match lines.next() {
+        Some((_, header)) if header.first().map(|h| h.to_lowercase()) == Some("date".into()) => {
+            header[1..].to_vec()
+        }
+        Some((line, _)) => {
+            return Err(ImportError::Parse(
+                line,
+                "expected a `Date` column first".into(),
+            ));
+        }
+        None => return Ok(Vec::new()),
+    };
+    let rows = lines
+        .map(|(line, fields)| Ok((line, parse_date(line, &fields[0])?, fields)))
+        .collect::<Result<Vec<_>, ImportError>>()?;
+
+    let mut records = Vec::new();
+    for (idx, habit) in habits.iter().enumerate() {
+        let values = rows
+            .iter()
+            .filter_map(|(line, date, fields)| {
+                let value = fields.get(idx + 1).filter(|v| !v.is_empty())?;
+                Some((*line, *date, value))
+            })
+            .collect::<Vec<_>>();
+        let checkmarks = ["-1", "0", "1", "2", "3"];
+        let yes_no = values
+            .iter()
+            .all(|(_, _, v)| checkmarks.contains(&v.as_str()));
+        for (line, date, value) in values {
+            let (value, goal) = if yes_no {
+                if value != "2" {
+                    continue;
+                }
+                ("1".to_string(), Some("1".to_string()))
+            } else {
+                if value.parse::<f64>().map(|v| v <= 0.).unwrap_or(false) {
+                    continue;
+                }
+                (value.to_string(), None)
+            };
+            records.push(Record {
+                line,
+                date,
+                habit: habit.to_string(),
+                value,
+                goal,
+            });
+        }
+    }
+    return Ok(records);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{write_rows, ExportFormat, Row};
+
+    #[test]
+    fn csv_with_and_without_header() {
+        let plain = "2020-06-01,walk,1\n2020-06-02,\"read, slowly\",3\n";
+        let records = read_records(ImportFormat::Csv, &mut plain.as_bytes()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].habit, "read, slowly");
+        assert_eq!(records[1].goal, None);
+
+        let exported = "date,habit,kind,value,goal,reached\n2020-06-01,cups,count,2,3,false\n";
+        let records = read_records(ImportFormat::Csv, &mut exported.as_bytes()).unwrap();
+        assert_eq!(records[0].value, "2");
+        assert_eq!(records[0].goal, Some("3".into()));
+    }
+
+    #[test]
+    fn csv_export_with_multi_line_notes_reads_back() {
+        let row = |day, note: &str| Row {
+            date: NaiveDate::from_ymd(2020, 6, day),
+            habit: "walk".into(),
+            kind: "count".into(),
+            value: day.to_string(),
+            goal: "1".into(),
+            reached: true,
+            note: note.into(),
+        };
+        let mut out = Vec::new();
+        let rows = [row(1, "rain,\n\"heavy\" rain"), row(2, "")];
+        write_rows(ExportFormat::Csv, &rows, &mut out).unwrap();
+        let records = read_records(ImportFormat::Csv, &mut out.as_slice()).unwrap();
+        let read = records
+            .iter()
+            .map(|r| (r.line, r.date, r.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(read, vec![(2, rows[0].date, "1"), (4, rows[1].date, "2")]);
+    }
+
+    #[test]
+    fn csv_invalid_date() {
+        let input = "2020-06-01,walk,1\n2020-13-01,walk,1\n";
+        match read_records(ImportFormat::Csv, &mut input.as_bytes()) {
+            Err(ImportError::Parse(line, _)) => assert_eq!(line, 2),
+            _ => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn loop_checkmarks() {
+        let input = "Date,Walk,Pages\n2020-06-02,2,12\n2020-06-01,1,0\n2020-05-31,-1,3\n";
+        let records = read_records(ImportFormat::Loop, &mut input.as_bytes()).unwrap();
+        let summary = records
+            .iter()
+            .map(|r| (r.habit.as_str(), r.date.to_string(), r.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("Walk", "2020-06-02".to_string(), "1"),
+                ("Pages", "2020-06-02".to_string(), "12"),
+                ("Pages", "2020-05-31".to_string(), "3"),
+            ]
+        );
+    }
+}
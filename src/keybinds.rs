@@ -1,64 +1,88 @@
 use std::convert::From;
+use std::fmt;
+use std::str::FromStr;
 
 use cursive::event::Event as CursiveEvent;
-use serde::ser;
-use serde::{self, Deserialize, Serialize, Serializer};
+use serde::de;
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Debug, PartialEq)]
-struct Event(CursiveEvent);
-
-macro_rules! event {
-    ($thing:expr) => {
-        Event { 0: $thing };
-    };
+pub enum KeyBindError {
+    InvalidBind(String),            // bind as written in the config
+    Conflict(Bind, String, String), // bind, both actions it is bound to
 }
 
-impl<T> From<T> for Event
-where
-    T: AsRef<str>,
-{
-    fn from(key: T) -> Self {
-        let key = key.as_ref();
-        if key.len() == 1 {
-            // single key
-            return event!(CursiveEvent::Char(key.chars().nth(0).unwrap()));
-        } else if (key.starts_with("c-") || key.starts_with("C-")) && key.len() == 3 {
-            // ctrl-key
-            return event!(CursiveEvent::CtrlChar(key.chars().nth(2).unwrap()));
-        } else {
-            panic!(
-                r"Invalid keybind in configuration!
-                    (I intend to handle this error gracefully in the near future)"
-            );
+impl std::error::Error for KeyBindError {}
+
+impl fmt::Display for KeyBindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyBindError::InvalidBind(s) => write!(
+                f,
+                "Invalid keybind `{}`, expected a single key, optionally prefixed with `C-` or `A-`",
+                s
+            ),
+            KeyBindError::Conflict(b, x, y) => {
+                write!(f, "Key `{}` is bound to both `{}` and `{}`", b, x, y)
+            }
         }
     }
 }
 
-enum Bind {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bind {
     Char(char),
     CtrlChar(char),
     AltChar(char),
 }
 
+impl FromStr for Bind {
+    type Err = KeyBindError;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let mut chars = key.chars();
+        match (chars.next(), chars.next(), chars.next(), chars.next()) {
+            // single key
+            (Some(c), None, _, _) => Ok(Bind::Char(c)),
+            // ctrl-key
+            (Some('c'), Some('-'), Some(c), None) | (Some('C'), Some('-'), Some(c), None) => {
+                Ok(Bind::CtrlChar(c))
+            }
+            // alt-key
+            (Some('a'), Some('-'), Some(c), None) | (Some('A'), Some('-'), Some(c), None) => {
+                Ok(Bind::AltChar(c))
+            }
+            _ => Err(KeyBindError::InvalidBind(key.into())),
+        }
+    }
+}
+
+impl fmt::Display for Bind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Bind::Char(c) => write!(f, "{}", c),
+            Bind::CtrlChar(c) => write!(f, "C-{}", c),
+            Bind::AltChar(c) => write!(f, "A-{}", c),
+        }
+    }
+}
+
 impl Serialize for Bind {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        match self {
-            Bind::Char(c) => serializer.serialize_newtype_variant("bind", 0, "regular", &c),
-            Bind::CtrlChar(c) => serializer.serialize_newtype_variant("bind", 0, "ctrl", &c),
-            Bind::AltChar(c) => serializer.serialize_newtype_variant("bind", 0, "alt", &c),
-        }
+        serializer.collect_str(self)
     }
 }
 
-impl Deserialize for Bind {
+impl<'de> Deserialize<'de> for Bind {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        eprintln!("hell = {:#?}", hell);
+        let key = String::deserialize(deserializer)?;
+        Bind::from_str(&key).map_err(de::Error::custom)
     }
 }
 
@@ -66,26 +90,115 @@ impl From<Bind> for CursiveEvent {
     fn from(key: Bind) -> Self {
         match key {
             Bind::Char(c) => CursiveEvent::Char(c),
-            Bind::CtrlChar(c) => CursiveEvent::Char(c),
+            Bind::CtrlChar(c) => CursiveEvent::CtrlChar(c),
             Bind::AltChar(c) => CursiveEvent::AltChar(c),
         }
     }
 }
 
+impl PartialEq<CursiveEvent> for Bind {
+    fn eq(&self, other: &CursiveEvent) -> bool {
+        match (self, other) {
+            (Bind::Char(a), CursiveEvent::Char(b)) => a == b,
+            (Bind::CtrlChar(a), CursiveEvent::CtrlChar(b)) => a == b,
+            (Bind::AltChar(a), CursiveEvent::AltChar(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+// Everything a key can be bound to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    FocusLeft,
+    FocusDown,
+    FocusUp,
+    FocusRight,
+    CursorLeft,
+    CursorDown,
+    CursorUp,
+    CursorRight,
+    WeekMode,
+    GlobalWeekMode,
+    MonthMode,
+    GlobalMonthMode,
+    YearMode,
+    GlobalYearMode,
+    MonthPrev,
+    MonthNext,
+    YearPrev,
+    YearNext,
+    ResetCursor,
+    Increment,
+    Decrement,
+    ClearMessage,
+    CommandMode,
+    Undo,
+    Redo,
+    Excuse,
+    GlobalExcuse,
+    Note,
+    Stats,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct KeyBinds {
-    grid: Movement,
-    cursor: Movement,
-    week_mode: Bind,
-    global_week_mode: Bind,
+    #[serde(default = "week_mode")]
+    pub week_mode: Bind,
+    #[serde(default = "global_week_mode")]
+    pub global_week_mode: Bind,
+    #[serde(default = "month_mode")]
+    pub month_mode: Bind,
+    #[serde(default = "global_month_mode")]
+    pub global_month_mode: Bind,
+    #[serde(default = "year_mode")]
+    pub year_mode: Bind,
+    #[serde(default = "global_year_mode")]
+    pub global_year_mode: Bind,
+    #[serde(default = "month_prev")]
+    pub month_prev: Bind,
+    #[serde(default = "month_next")]
+    pub month_next: Bind,
+    #[serde(default = "year_prev")]
+    pub year_prev: Bind,
+    #[serde(default = "year_next")]
+    pub year_next: Bind,
+    #[serde(default = "reset_cursor")]
+    pub reset_cursor: Bind,
+    #[serde(default = "increment")]
+    pub increment: Bind,
+    #[serde(default = "decrement")]
+    pub decrement: Bind,
+    #[serde(default = "clear_message")]
+    pub clear_message: Bind,
+    #[serde(default = "command_mode")]
+    pub command_mode: Bind,
+    #[serde(default = "undo")]
+    pub undo: Bind,
+    #[serde(default = "redo")]
+    pub redo: Bind,
+    #[serde(default = "excuse")]
+    pub excuse: Bind,
+    #[serde(default = "global_excuse")]
+    pub global_excuse: Bind,
+    #[serde(default = "note")]
+    pub note: Bind,
+    #[serde(default = "stats")]
+    pub stats: Bind,
+
+    // tables go last, toml cannot have plain values after them
+    #[serde(default = "grid", deserialize_with = "partial_grid")]
+    pub grid: Movement,
+    #[serde(default = "cursor", deserialize_with = "partial_cursor")]
+    pub cursor: Movement,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Movement {
-    up: Bind,
-    down: Bind,
-    left: Bind,
-    right: Bind,
+    pub up: Bind,
+    pub down: Bind,
+    pub left: Bind,
+    pub right: Bind,
 }
 
 impl Movement {
@@ -99,19 +212,205 @@ impl Movement {
     }
 }
 
+fn grid() -> Movement {
+    Movement::new('h', 'j', 'k', 'l')
+}
+fn cursor() -> Movement {
+    Movement::new('H', 'J', 'K', 'L')
+}
+
+// A movement table as written in the config, keys that are left
+// out keep their defaults
+#[derive(Deserialize)]
+struct PartialMovement {
+    up: Option<Bind>,
+    down: Option<Bind>,
+    left: Option<Bind>,
+    right: Option<Bind>,
+}
+
+impl PartialMovement {
+    fn or(self, default: Movement) -> Movement {
+        return Movement {
+            up: self.up.unwrap_or(default.up),
+            down: self.down.unwrap_or(default.down),
+            left: self.left.unwrap_or(default.left),
+            right: self.right.unwrap_or(default.right),
+        };
+    }
+}
+
+fn partial_grid<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Movement, D::Error> {
+    return Ok(PartialMovement::deserialize(deserializer)?.or(grid()));
+}
+fn partial_cursor<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Movement, D::Error> {
+    return Ok(PartialMovement::deserialize(deserializer)?.or(cursor()));
+}
+fn week_mode() -> Bind {
+    Bind::Char('v')
+}
+fn global_week_mode() -> Bind {
+    Bind::Char('V')
+}
+fn month_mode() -> Bind {
+    Bind::Char('m')
+}
+fn global_month_mode() -> Bind {
+    Bind::Char('M')
+}
+fn year_mode() -> Bind {
+    Bind::Char('y')
+}
+fn global_year_mode() -> Bind {
+    Bind::Char('Y')
+}
+fn month_prev() -> Bind {
+    Bind::Char('[')
+}
+fn month_next() -> Bind {
+    Bind::Char(']')
+}
+fn year_prev() -> Bind {
+    Bind::Char('(')
+}
+fn year_next() -> Bind {
+    Bind::Char(')')
+}
+fn reset_cursor() -> Bind {
+    Bind::Char('}')
+}
+fn increment() -> Bind {
+    Bind::Char('n')
+}
+fn decrement() -> Bind {
+    Bind::Char('p')
+}
+fn clear_message() -> Bind {
+    Bind::CtrlChar('l')
+}
+fn command_mode() -> Bind {
+    Bind::Char(':')
+}
+fn undo() -> Bind {
+    Bind::Char('u')
+}
+fn redo() -> Bind {
+    Bind::CtrlChar('r')
+}
+fn excuse() -> Bind {
+    Bind::Char('x')
+}
+fn global_excuse() -> Bind {
+    Bind::Char('X')
+}
+fn note() -> Bind {
+    Bind::Char('N')
+}
+fn stats() -> Bind {
+    Bind::Char('s')
+}
+
 impl std::default::Default for KeyBinds {
     fn default() -> Self {
-        let grid = Movement::new('h', 'j', 'k', 'l');
-        let cursor = Movement::new('H', 'J', 'K', 'L');
         return KeyBinds {
-            grid,
-            cursor,
-            week_mode: Bind::Char('v'),
-            global_week_mode: Bind::Char('V'),
+            week_mode: week_mode(),
+            global_week_mode: global_week_mode(),
+            month_mode: month_mode(),
+            global_month_mode: global_month_mode(),
+            year_mode: year_mode(),
+            global_year_mode: global_year_mode(),
+            month_prev: month_prev(),
+            month_next: month_next(),
+            year_prev: year_prev(),
+            year_next: year_next(),
+            reset_cursor: reset_cursor(),
+            increment: increment(),
+            decrement: decrement(),
+            clear_message: clear_message(),
+            command_mode: command_mode(),
+            undo: undo(),
+            redo: redo(),
+            excuse: excuse(),
+            global_excuse: global_excuse(),
+            note: note(),
+            stats: stats(),
+            grid: grid(),
+            cursor: cursor(),
         };
     }
 }
 
+impl KeyBinds {
+    // every binding, along with its name in the config file
+    fn bindings(&self) -> Vec<(&'static str, Bind, Action)> {
+        vec![
+            ("grid.left", self.grid.left, Action::FocusLeft),
+            ("grid.down", self.grid.down, Action::FocusDown),
+            ("grid.up", self.grid.up, Action::FocusUp),
+            ("grid.right", self.grid.right, Action::FocusRight),
+            ("cursor.left", self.cursor.left, Action::CursorLeft),
+            ("cursor.down", self.cursor.down, Action::CursorDown),
+            ("cursor.up", self.cursor.up, Action::CursorUp),
+            ("cursor.right", self.cursor.right, Action::CursorRight),
+            ("week_mode", self.week_mode, Action::WeekMode),
+            (
+                "global_week_mode",
+                self.global_week_mode,
+                Action::GlobalWeekMode,
+            ),
+            ("month_mode", self.month_mode, Action::MonthMode),
+            (
+                "global_month_mode",
+                self.global_month_mode,
+                Action::GlobalMonthMode,
+            ),
+            ("year_mode", self.year_mode, Action::YearMode),
+            (
+                "global_year_mode",
+                self.global_year_mode,
+                Action::GlobalYearMode,
+            ),
+            ("month_prev", self.month_prev, Action::MonthPrev),
+            ("month_next", self.month_next, Action::MonthNext),
+            ("year_prev", self.year_prev, Action::YearPrev),
+            ("year_next", self.year_next, Action::YearNext),
+            ("reset_cursor", self.reset_cursor, Action::ResetCursor),
+            ("increment", self.increment, Action::Increment),
+            ("decrement", self.decrement, Action::Decrement),
+            ("clear_message", self.clear_message, Action::ClearMessage),
+            ("command_mode", self.command_mode, Action::CommandMode),
+            ("undo", self.undo, Action::Undo),
+            ("redo", self.redo, Action::Redo),
+            ("excuse", self.excuse, Action::Excuse),
+            ("global_excuse", self.global_excuse, Action::GlobalExcuse),
+            ("note", self.note, Action::Note),
+            ("stats", self.stats, Action::Stats),
+        ]
+    }
+
+    pub fn action(&self, e: &CursiveEvent) -> Option<Action> {
+        self.bindings()
+            .into_iter()
+            .find(|(_, bind, _)| bind == e)
+            .map(|(_, _, action)| action)
+    }
+
+    // a key may only be bound to a single action
+    pub fn validate(&self) -> Result<(), KeyBindError> {
+        let bindings = self.bindings();
+        for (idx, (name, bind, _)) in bindings.iter().enumerate() {
+            if let Some((other, _, _)) = bindings[idx + 1..].iter().find(|(_, b, _)| b == bind) {
+                return Err(KeyBindError::Conflict(
+                    *bind,
+                    name.to_string(),
+                    other.to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -120,27 +419,52 @@ mod tests {
     fn normal_keybind() {
         let bind = "X";
         let expected = CursiveEvent::Char('X');
-        assert_eq!(Event::from(bind), event!(expected));
+        assert_eq!(Bind::from_str(bind).unwrap(), expected);
     }
 
     #[test]
     fn control_keybind() {
         let bind = "C-x";
         let expected = CursiveEvent::CtrlChar('x');
-        assert_eq!(Event::from(bind), event!(expected));
+        assert_eq!(Bind::from_str(bind).unwrap(), expected);
     }
 
     #[test]
     fn lower_case_control_keybind() {
         let bind = "c-x";
         let expected = CursiveEvent::CtrlChar('x');
-        assert_eq!(Event::from(bind), event!(expected));
+        assert_eq!(Bind::from_str(bind).unwrap(), expected);
+    }
+
+    #[test]
+    fn alt_keybind() {
+        let bind = "A-x";
+        let expected = CursiveEvent::AltChar('x');
+        assert_eq!(Bind::from_str(bind).unwrap(), expected);
     }
 
     #[test]
-    #[should_panic]
     fn very_long_and_wrong_keybind() {
         let bind = "alksdjfalkjdf";
-        Event::from(bind);
+        assert_eq!(
+            Bind::from_str(bind),
+            Err(KeyBindError::InvalidBind(bind.into()))
+        );
+    }
+
+    #[test]
+    fn conflicting_keybinds() {
+        let mut binds = KeyBinds::default();
+        assert!(binds.validate().is_ok());
+        binds.week_mode = Bind::Char('h');
+        assert!(binds.validate().is_err());
+    }
+
+    #[test]
+    fn partial_movement_tables() {
+        let binds: KeyBinds = toml::from_str("[grid]\nleft = \"a\"\n").unwrap();
+        assert_eq!(binds.grid.left, Bind::Char('a'));
+        assert_eq!(binds.grid.down, Bind::Char('j'));
+        assert_eq!(binds.cursor.left, Bind::Char('H'));
     }
 }
@@ -2,13 +2,25 @@
 
 mod app;
 mod command;
+mod export;
 mod habit;
+mod import;
+mod keybinds;
+mod note;
+mod stats;
 mod theme;
 mod utils;
 mod views;
 
-use crate::app::App;
-use crate::command::{open_command_window, Command};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::process;
+use std::str::FromStr;
+
+use crate::app::{App, MessageKind};
+use crate::command::{open_command_window, Command, CommandLineError};
+use crate::export::ExportFormat;
+use crate::import::{ImportError, ImportFormat};
 use crate::utils::{load_configuration_file, AppConfig};
 
 use clap::{App as ClapApp, Arg};
@@ -19,6 +31,7 @@ use cursive::termion;
 #[cfg(feature = "crossterm-backend")]
 use cursive::crossterm;
 
+use cursive::event::Event;
 use cursive::views::{LinearLayout, NamedView};
 use lazy_static::lazy_static;
 
@@ -27,6 +40,9 @@ lazy_static! {
 }
 
 fn main() {
+    // report configuration errors before the terminal is taken over
+    lazy_static::initialize(&CONFIGURATION);
+
     let matches = ClapApp::new(env!("CARGO_PKG_NAME"))
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
@@ -47,25 +63,163 @@ fn main() {
                 .help("list dijo habits")
                 .conflicts_with("command"),
         )
+        .arg(
+            Arg::with_name("archived")
+                .long("archived")
+                .takes_value(false)
+                .help("list archived habits instead")
+                .requires("list"),
+        )
+        .arg(
+            Arg::with_name("export")
+                .short("e")
+                .long("export")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .help("export habit history as csv, jsonl or markdown")
+                .conflicts_with_all(&["command", "list"]),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .value_name("PATH")
+                .help("file to export to, defaults to stdout")
+                .requires("export"),
+        )
+        .arg(
+            Arg::with_name("import")
+                .short("i")
+                .long("import")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .help("import habit history from csv or a Loop Habit Tracker export")
+                .conflicts_with_all(&["command", "list", "export"]),
+        )
+        .arg(
+            Arg::with_name("input")
+                .long("input")
+                .takes_value(true)
+                .value_name("PATH")
+                .help("file to import from, defaults to stdin")
+                .requires("import"),
+        )
         .get_matches();
     if let Some(c) = matches.value_of("command") {
-        let command = Command::from_string(c);
-        match command {
-            Ok(Command::TrackUp(_)) | Ok(Command::TrackDown(_)) => {
+        match Command::from_string(c) {
+            Ok(command) if command.is_interactive() => {
+                let name = command::tokenize(c).unwrap_or_default().remove(0);
+                eprintln!("{}", CommandLineError::InteractiveOnly(name));
+                process::exit(2);
+            }
+            Ok(command) => {
+                // hold the lock from load to save, so that concurrent
+                // runs see each other's changes
+                let _lock = utils::DataLock::exclusive().unwrap_or_else(|e| {
+                    eprintln!("Unable to lock habit files: {}", e);
+                    process::exit(1);
+                });
                 let mut app = App::load_state();
-                app.parse_command(command);
-                app.save_state();
+                // problems loading the saved state are worth a warning
+                if let MessageKind::Error = app.message().kind() {
+                    eprintln!("{}", app.message().contents());
+                }
+                app.clear_message();
+                app.parse_command(Ok(command));
+                let message = app.message();
+                if let MessageKind::Error = message.kind() {
+                    eprintln!("{}", message.contents());
+                    process::exit(1);
+                }
+                if !message.contents().is_empty() {
+                    println!("{}", message.contents());
+                }
+                if let Err(e) = app.save_state() {
+                    eprintln!("Unable to save habits: {}", e);
+                    process::exit(1);
+                }
             }
             Err(e) => {
                 eprintln!("{}", e);
+                process::exit(2);
+            }
+        }
+    } else if let Some(format) = matches.value_of("export") {
+        let format = ExportFormat::from_str(format).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(2);
+        });
+        let app = {
+            let _lock = utils::DataLock::shared();
+            App::load_state()
+        };
+        if let MessageKind::Error = app.message().kind() {
+            eprintln!("{}", app.message().contents());
+        }
+        let exported = match matches.value_of("output") {
+            Some(path) => {
+                File::create(path).and_then(|f| app.export(format, &mut BufWriter::new(f)))
+            }
+            None => app.export(format, &mut io::stdout().lock()),
+        };
+        if let Err(e) = exported {
+            eprintln!("Unable to export habits: {}", e);
+            process::exit(1);
+        }
+    } else if let Some(format) = matches.value_of("import") {
+        let format = ImportFormat::from_str(format).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(2);
+        });
+        let records = match matches.value_of("input") {
+            Some(path) => File::open(path)
+                .map_err(ImportError::from)
+                .and_then(|mut f| import::read_records(format, &mut f)),
+            None => import::read_records(format, &mut io::stdin().lock()),
+        };
+        let _lock = utils::DataLock::exclusive().unwrap_or_else(|e| {
+            eprintln!("Unable to lock habit files: {}", e);
+            process::exit(1);
+        });
+        let mut app = App::load_state();
+        if let MessageKind::Error = app.message().kind() {
+            eprintln!("{}", app.message().contents());
+        }
+        match records.and_then(|records| app.import(records)) {
+            Ok(report) => {
+                for conflict in &report.conflicts {
+                    eprintln!("{}", conflict);
+                }
+                println!("{}", report);
+            }
+            Err(e) => {
+                eprintln!("Unable to import habits: {}", e);
+                process::exit(1);
             }
-            _ => eprintln!(
-                "Commands other than `track-up` and `track-down` are currently not supported!"
-            ),
+        }
+        if let Err(e) = app.save_state() {
+            eprintln!("Unable to save habits: {}", e);
+            process::exit(1);
         }
     } else if matches.is_present("list") {
-        for h in App::load_state().list_habits() {
-            println!("{}", h);
+        let app = {
+            let _lock = utils::DataLock::shared();
+            App::load_state()
+        };
+        if let MessageKind::Error = app.message().kind() {
+            eprintln!("{}", app.message().contents());
+        }
+        let streaks = app.list_streaks(matches.is_present("archived"));
+        let width = streaks.iter().map(|(h, _, _)| h.len()).max().unwrap_or(0);
+        for (h, current, longest) in streaks {
+            println!(
+                "{:width$}  streak: {} (longest: {})",
+                h,
+                current,
+                longest,
+                width = width
+            );
         }
     } else {
         #[cfg(any(feature = "termion-backend", feature = "default"))]
@@ -74,17 +228,28 @@ fn main() {
         #[cfg(feature = "crossterm-backend")]
         let mut s = crossterm();
 
-        let app = App::load_state();
+        let app = {
+            let _lock = utils::DataLock::shared();
+            App::load_state()
+        };
         let layout = NamedView::new(
             "Frame",
             LinearLayout::vertical().child(NamedView::new("Main", app)),
         );
         s.add_layer(layout);
-        s.add_global_callback(':', |s| open_command_window(s));
+        s.add_global_callback(Event::from(CONFIGURATION.keybinds.command_mode), |s| {
+            open_command_window(s)
+        });
 
         s.set_theme(theme::theme_gen());
         s.run();
 
-        s.call_on_name("Main", |app: &mut App| app.save_state());
+        let saved = s.call_on_name("Main", |app: &mut App| {
+            utils::DataLock::exclusive().and_then(|_lock| app.save_state())
+        });
+        if let Some(Err(e)) = saved {
+            eprintln!("Unable to save habits: {}", e);
+            process::exit(1);
+        }
     }
 }
@@ -0,0 +1,40 @@
+use cursive::event::Key;
+use cursive::view::{Nameable, Resizable};
+use cursive::views::{Dialog, OnEventView, TextArea};
+use cursive::Cursive;
+
+use crate::app::App;
+
+// Opens a popup to edit the note of the focused habit on the cursor
+// date, saving an empty note removes it
+pub fn open_note_window(s: &mut Cursive) {
+    let focused = s
+        .call_on_name("Main", |view: &mut App| view.focused_note())
+        .flatten();
+    let (name, date, note) = match focused {
+        Some(f) => f,
+        None => return,
+    };
+    let title = format!("{} on {}", name, date);
+    let editor = TextArea::new()
+        .content(note.unwrap_or_default())
+        .with_name("Note")
+        .min_size((40, 5));
+    let dialog = Dialog::around(editor)
+        .title(title)
+        .button("Save", move |s| {
+            let note = s
+                .call_on_name("Note", |view: &mut TextArea| view.get_content().to_string())
+                .unwrap_or_default();
+            s.call_on_name("Main", |view: &mut App| {
+                view.clear_message();
+                view.set_note(&name, date, &note);
+                view.save_changes();
+            });
+            s.pop_layer();
+        })
+        .dismiss_button("Cancel");
+    s.add_layer(OnEventView::new(dialog).on_event(Key::Esc, |s| {
+        s.pop_layer();
+    }));
+}
@@ -0,0 +1,92 @@
+use chrono::NaiveDate;
+use cursive::event::Key;
+use cursive::view::{Resizable, Scrollable};
+use cursive::views::{OnEventView, Panel, TextView};
+use cursive::Cursive;
+
+use crate::app::App;
+use crate::command::Goal;
+use crate::habit::{Period, Stats};
+
+fn rate(rate: Option<u32>) -> String {
+    match rate {
+        Some(r) => format!("{}%", r),
+        None => "-".into(),
+    }
+}
+
+// the statistics of a habit, laid out as a table
+fn render(goal: Goal, stats: &Stats) -> String {
+    let unit = match goal.period {
+        Period::Day => "days",
+        Period::Week => "weeks",
+        Period::Month => "months",
+    };
+    let since = stats
+        .since
+        .map(|d| d.to_string())
+        .unwrap_or_else(|| "-".into());
+    let mut lines = vec![
+        format!("{:<16}{:>10}", "Goal", goal.to_string()),
+        format!("{:<16}{:>10}", "Tracking since", since),
+        String::new(),
+        format!("{:<16}{:>10}", "Completions", stats.completions),
+        format!(
+            "{:<16}{:>10}",
+            "Current streak",
+            format!("{} {}", stats.current_streak, unit)
+        ),
+        format!(
+            "{:<16}{:>10}",
+            "Best streak",
+            format!("{} {}", stats.longest_streak, unit)
+        ),
+    ];
+    for (days, r) in &stats.rates {
+        let label = format!("Last {} days", days);
+        lines.push(format!("{:<16}{:>10}", label, rate(*r)));
+    }
+    if let Some(avg) = stats.average {
+        lines.push(format!("{:<16}{:>10.2}", "Average", avg));
+    }
+    let best = stats
+        .best_weekday
+        .map(|d| NaiveDate::from_isoywd(2020, 1, d).format("%A").to_string())
+        .unwrap_or_else(|| "-".into());
+    lines.push(format!("{:<16}{:>10}", "Best weekday", best));
+
+    lines.push(String::new());
+    lines.push(format!("{:<16}{:>5}{:>5}", "Month", "Done", "Rate"));
+    for m in &stats.months {
+        let month = NaiveDate::from_ymd(m.year, m.month, 1).format("%b %Y");
+        lines.push(format!(
+            "{:<16}{:>5}{:>5}",
+            month.to_string(),
+            m.completions,
+            rate(m.rate)
+        ));
+    }
+    return lines.join("\n");
+}
+
+// Opens a full screen view of the statistics of habit `name`,
+// closed with Esc or q
+pub fn open_stats_window(s: &mut Cursive, name: &str) {
+    let stats = s
+        .call_on_name("Main", |view: &mut App| view.habit_stats(name))
+        .flatten();
+    let (goal, stats) = match stats {
+        Some(s) => s,
+        None => return,
+    };
+    let view = Panel::new(TextView::new(render(goal, &stats)).scrollable()).title(name);
+    s.add_fullscreen_layer(
+        OnEventView::new(view.full_screen())
+            .on_event(Key::Esc, |s| {
+                s.pop_layer();
+            })
+            .on_event('q', |s| {
+                s.pop_layer();
+            }),
+    );
+}
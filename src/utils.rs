@@ -1,16 +1,22 @@
 use cursive::theme::{BaseColor, Color};
 use directories::ProjectDirs;
+use fs2::FileExt;
 use serde::{Deserialize, Serialize};
 
 use std;
 use std::default::Default;
 use std::fs::{self, File, OpenOptions};
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use chrono::{Datelike, Duration, Local, NaiveDate};
+
+use crate::keybinds::KeyBinds;
+use crate::CONFIGURATION;
 
 pub const VIEW_WIDTH: usize = 25;
-pub const VIEW_HEIGHT: usize = 8;
-pub const GRID_WIDTH: usize = 3;
+pub const VIEW_HEIGHT: usize = 9;
 
 #[derive(Serialize, Deserialize)]
 pub struct Characters {
@@ -20,11 +26,16 @@ pub struct Characters {
     pub false_chr: char,
     #[serde(default = "base_char")]
     pub future_chr: char,
+    #[serde(default = "excused_char")]
+    pub excused_chr: char,
 }
 
 fn base_char() -> char {
     '·'
 }
+fn excused_char() -> char {
+    '~'
+}
 
 impl Default for Characters {
     fn default() -> Self {
@@ -32,6 +43,7 @@ impl Default for Characters {
             true_chr: '·',
             false_chr: '·',
             future_chr: '·',
+            excused_chr: excused_char(),
         }
     }
 }
@@ -44,6 +56,8 @@ pub struct Colors {
     pub todo: String,
     #[serde(default = "light_black")]
     pub inactive: String,
+    #[serde(default = "blue")]
+    pub excused: String,
 }
 
 fn cyan() -> String {
@@ -55,6 +69,9 @@ fn magenta() -> String {
 fn light_black() -> String {
     "light black".into()
 }
+fn blue() -> String {
+    "blue".into()
+}
 
 impl Default for Colors {
     fn default() -> Self {
@@ -62,24 +79,66 @@ impl Default for Colors {
             reached: cyan(),
             todo: magenta(),
             inactive: light_black(),
+            excused: blue(),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WeekStart {
+    #[default]
+    Monday,
+    Sunday,
+}
+
+impl WeekStart {
+    // the first day of the week that `date` falls in
+    pub fn week_of(&self, date: NaiveDate) -> NaiveDate {
+        let since = match self {
+            WeekStart::Monday => date.weekday().num_days_from_monday(),
+            WeekStart::Sunday => date.weekday().num_days_from_sunday(),
+        };
+        return date - Duration::days(since as i64);
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct AppConfig {
+    // number of backups kept per data file
+    #[serde(default = "default_backups")]
+    pub backups: usize,
+
+    // habits per row of the grid, fits the terminal when unset
+    #[serde(default)]
+    pub grid_columns: Option<usize>,
+
+    #[serde(default)]
+    pub week_start: WeekStart,
+
     #[serde(default)]
     pub look: Characters,
 
     #[serde(default)]
     pub colors: Colors,
+
+    #[serde(default)]
+    pub keybinds: KeyBinds,
+}
+
+fn default_backups() -> usize {
+    5
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         AppConfig {
+            backups: default_backups(),
+            grid_columns: None,
+            week_start: Default::default(),
             look: Default::default(),
             colors: Default::default(),
+            keybinds: Default::default(),
         }
     }
 }
@@ -95,6 +154,13 @@ impl AppConfig {
     pub fn inactive_color(&self) -> Color {
         return Color::parse(&self.colors.inactive).unwrap_or(Color::Light(BaseColor::Black));
     }
+    pub fn excused_color(&self) -> Color {
+        return Color::parse(&self.colors.excused).unwrap_or(Color::Dark(BaseColor::Blue));
+    }
+    // columns of habits in a grid `width` cells wide
+    pub fn grid_columns(&self, width: usize) -> usize {
+        return self.grid_columns.unwrap_or(width / (VIEW_WIDTH + 2)).max(1);
+    }
 }
 
 pub fn load_configuration_file() -> AppConfig {
@@ -102,7 +168,16 @@ pub fn load_configuration_file() -> AppConfig {
     if let Ok(ref mut f) = File::open(&cf) {
         let mut j = String::new();
         f.read_to_string(&mut j);
-        return toml::from_str(&j).unwrap_or_else(|e| panic!("Invalid config file: `{}`", e));
+        let config = toml::from_str::<AppConfig>(&j)
+            .map_err(|e| e.to_string())
+            .and_then(|c| c.keybinds.validate().map(|_| c).map_err(|e| e.to_string()));
+        match config {
+            Ok(c) => return c,
+            Err(e) => {
+                eprintln!("Invalid config file `{}`: {}", cf.display(), e);
+                process::exit(1);
+            }
+        }
     } else {
         if let Ok(dc) = toml::to_string(&AppConfig::default()) {
             match OpenOptions::new().create(true).write(true).open(&cf) {
@@ -142,3 +217,170 @@ pub fn auto_habit_file() -> PathBuf {
     data_file.push("habit_record[auto].json");
     return data_file;
 }
+
+pub fn lock_file() -> PathBuf {
+    let proj_dirs = project_dirs();
+    let mut lock_file = PathBuf::from(proj_dirs.data_dir());
+    fs::create_dir_all(&lock_file);
+    lock_file.push("dijo.lock");
+    return lock_file;
+}
+
+// An advisory lock on the data files, shared between readers and
+// held exclusively by writers, so that the interactive program and
+// `dijo -c` never work on the same file at once. The lock is
+// released when dropped. Locks are not reentrant, do not take one
+// while another is held by the same process.
+pub struct DataLock(File);
+
+impl DataLock {
+    fn open() -> io::Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(lock_file())
+    }
+    pub fn shared() -> io::Result<Self> {
+        let f = DataLock::open()?;
+        FileExt::lock_shared(&f)?;
+        Ok(DataLock(f))
+    }
+    pub fn exclusive() -> io::Result<Self> {
+        let f = DataLock::open()?;
+        FileExt::lock_exclusive(&f)?;
+        Ok(DataLock(f))
+    }
+}
+
+impl Drop for DataLock {
+    fn drop(&mut self) {
+        FileExt::unlock(&self.0);
+    }
+}
+
+// backups are kept in a `backups` directory next to the file
+fn backup_dir(file: &Path) -> PathBuf {
+    let mut backup_dir = file.parent().map(PathBuf::from).unwrap_or_default();
+    backup_dir.push("backups");
+    return backup_dir;
+}
+
+// backups of `file`, newest first
+pub fn backups_of(file: &Path) -> Vec<PathBuf> {
+    let prefix = match file.file_name() {
+        Some(name) => format!("{}.", name.to_string_lossy()),
+        None => return Vec::new(),
+    };
+    let mut backups = match fs::read_dir(backup_dir(file)) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| {
+                p.file_name()
+                    .map(|n| n.to_string_lossy().starts_with(&prefix))
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<_>>(),
+        Err(_) => Vec::new(),
+    };
+    // timestamps sort lexically
+    backups.sort();
+    backups.reverse();
+    return backups;
+}
+
+// write `data`, the new contents of `file`, into the backup of it
+// for `day`, and drop the oldest backups beyond `limit`. There is
+// one backup per day, holding the last version saved that day, so
+// a bad save only ever replaces the backup of the current day.
+fn backup(file: &Path, data: &[u8], day: NaiveDate, limit: usize) -> io::Result<()> {
+    let name = match file.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => return Ok(()),
+    };
+    let mut target = backup_dir(file);
+    fs::create_dir_all(&target)?;
+    target.push(format!("{}.{}", name, day.format("%Y%m%d")));
+    fs::write(target, data)?;
+    for old in backups_of(file).iter().skip(limit) {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+// Replace the contents of `file` with `data`. The data is written
+// and synced to a temporary file, which is then renamed over
+// `file`, so a crash can never leave a partially written file
+// behind. Once `file` is replaced, the new contents are backed up
+// as well, so the newest backup always matches the last save.
+pub fn write_atomically(file: &Path, data: &[u8]) -> io::Result<()> {
+    let mut tmp = file.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    {
+        let mut f = File::create(&tmp)?;
+        f.write_all(data)?;
+        f.sync_all()?;
+    }
+    fs::rename(&tmp, file)?;
+    // the rename is only durable once the directory is synced,
+    // this is not possible on every platform, ignore failures
+    if let Some(Ok(dir)) = file.parent().map(File::open) {
+        dir.sync_all();
+    }
+    if CONFIGURATION.backups > 0 {
+        let today = Local::now().naive_local().date();
+        backup(file, data, today, CONFIGURATION.backups)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an empty directory for a single test
+    fn scratch_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dijo-{}-{}", test, process::id()));
+        fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        return dir;
+    }
+
+    #[test]
+    fn one_backup_per_day_newest_first() {
+        let dir = scratch_dir("backups");
+        let file = dir.join("habit_record.json");
+        let other = dir.join("habit_record[auto].json");
+        let day = |d| NaiveDate::from_ymd(2021, 3, d);
+        for (d, data) in &[(1, "1"), (2, "2"), (2, "2b"), (3, "3"), (4, "4")] {
+            backup(&file, data.as_bytes(), day(*d), 3).unwrap();
+        }
+        backup(&other, b"auto", day(4), 3).unwrap();
+
+        let contents = backups_of(&file)
+            .iter()
+            .map(|b| fs::read_to_string(b).unwrap())
+            .collect::<Vec<_>>();
+        // the last save of a day replaces its backup
+        assert_eq!(contents, vec!["4", "3", "2b"]);
+        assert_eq!(backups_of(&other).len(), 1);
+        fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn newest_backup_matches_written_data() {
+        let dir = scratch_dir("write");
+        let file = dir.join("habit_record.json");
+        write_atomically(&file, b"first").unwrap();
+        write_atomically(&file, b"second").unwrap();
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "second");
+        if CONFIGURATION.backups > 0 {
+            let newest = &backups_of(&file)[0];
+            assert_eq!(fs::read_to_string(newest).unwrap(), "second");
+        }
+        fs::remove_dir_all(&dir);
+    }
+}
@@ -5,14 +5,38 @@ use cursive::view::{CannotFocus, View};
 use cursive::{Printer, Vec2};
 
 use chrono::prelude::*;
-use chrono::{Local, NaiveDate};
+use chrono::{Duration, Local, NaiveDate};
 
-use crate::habit::{Bit, Count, Float, Habit, TrackEvent, ViewMode};
+use crate::habit::{
+    completions, progress, Addiction, Bit, Count, Float, Habit, TrackEvent, ViewMode,
+};
 use crate::theme::cursor_bg;
-use crate::utils::VIEW_WIDTH;
+use crate::utils::{VIEW_HEIGHT, VIEW_WIDTH};
 
 use crate::CONFIGURATION;
 
+// every day of the given month
+fn month_days(year: i32, month: u32) -> Vec<NaiveDate> {
+    (1..=31)
+        .filter_map(|i| NaiveDate::from_ymd_opt(year, month, i))
+        .collect()
+}
+
+// the weeks overlapping the given month. Every week is 7 days long
+// and starts on the configured first day of the week, so the first
+// and last weeks may reach into the neighbouring months.
+fn month_weeks(year: i32, month: u32) -> Vec<Vec<NaiveDate>> {
+    let days = month_days(year, month);
+    let last = *days.last().unwrap();
+    let mut start = CONFIGURATION.week_start.week_of(days[0]);
+    let mut weeks = Vec::new();
+    while start <= last {
+        weeks.push((0..7).map(|i| start + Duration::days(i)).collect());
+        start += Duration::weeks(1);
+    }
+    return weeks;
+}
+
 pub trait ShadowView {
     fn draw(&self, printer: &Printer);
     fn required_size(&mut self, _: Vec2) -> Vec2;
@@ -47,6 +71,12 @@ where
 
         let goal_status = is_today && self.reached_goal(Local::now().naive_local().date());
 
+        // current streak, followed by the longest one
+        let streak = match (self.current_streak(), self.longest_streak()) {
+            (_, 0) => String::new(),
+            (current, longest) => format!("{} ({})", current, longest),
+        };
+
         printer.with_style(
             Style::merge(&[
                 if goal_status {
@@ -63,21 +93,22 @@ where
             |p| {
                 p.print(
                     (0, 0),
-                    &format!(" {:.width$} ", self.name(), width = VIEW_WIDTH - 6),
+                    &format!(
+                        " {:.width$} ",
+                        self.name(),
+                        width = VIEW_WIDTH - 7 - streak.len()
+                    ),
                 );
             },
         );
+        printer.with_style(future_style, |p| {
+            p.print((VIEW_WIDTH - 4 - streak.len(), 0), &streak);
+        });
 
         let draw_week = |printer: &Printer| {
-            let days = (1..31)
-                .map(|i| NaiveDate::from_ymd_opt(year, month, i))
-                .flatten() // dates 28-31 may not exist, ignore them if they don't
-                .collect::<Vec<_>>();
-            for (week, line_nr) in days.chunks(7).zip(2..) {
-                let weekly_goal = self.goal() * week.len() as u32;
+            for (week, line_nr) in month_weeks(year, month).iter().zip(2..) {
+                let (completions, weekly_goal) = completions(self, week);
                 let is_this_week = week.contains(&Local::now().naive_local().date());
-                let remaining = week.iter().map(|&i| self.remaining(i)).sum::<u32>();
-                let completions = weekly_goal - remaining;
                 let full = VIEW_WIDTH - 8;
                 let bars_to_fill = if weekly_goal > 0 {
                     (completions * full as u32) / weekly_goal
@@ -109,10 +140,49 @@ where
         };
 
         let draw_day = |printer: &Printer| {
-            let mut i = 0;
-            while let Some(d) = NaiveDate::from_ymd_opt(year, month, i + 1) {
-                let mut day_style = Style::none();
-                let mut fs = future_style;
+            let weeks = month_weeks(year, month);
+            for (col, d) in weeks[0].iter().enumerate() {
+                let label = d.format("%a").to_string();
+                printer.with_style(future_style, |p| {
+                    p.print((col * 3, 1), &format!("{:^3}", &label[..2]));
+                });
+            }
+            let days = weeks.iter().enumerate().flat_map(|(row, week)| {
+                week.iter()
+                    .enumerate()
+                    .filter(|(_, d)| d.month() == month)
+                    .map(move |(col, &d)| (d, Vec2::new(col * 3, row + 2)))
+            });
+            for (d, coords) in days {
+                // days with a note are underlined
+                let noted = match self.note(d) {
+                    Some(_) => Style::from(Effect::Underline),
+                    None => Style::none(),
+                };
+                if self.is_excused(d) {
+                    let mut style = Style::from(CONFIGURATION.excused_color()).combine(noted);
+                    if d == now && printer.focused {
+                        style = style.combine(ColorStyle::back(cursor_bg()));
+                    }
+                    printer.with_style(style, |p| {
+                        p.print(coords, &format!("{:^3}", CONFIGURATION.look.excused_chr));
+                    });
+                    continue;
+                }
+                if !self.is_active(d) {
+                    // off the schedule, neither reached nor missed
+                    let mut style = future_style.combine(noted);
+                    if d == now && printer.focused {
+                        style = style.combine(ColorStyle::back(cursor_bg()));
+                    }
+                    let value = self.get_by_date(d).map(|c| format!("{:^3}", c));
+                    printer.with_style(style, |p| {
+                        p.print(coords, &value.unwrap_or_else(|| " ".repeat(3)));
+                    });
+                    continue;
+                }
+                let mut day_style = noted;
+                let mut fs = future_style.combine(noted);
                 let grs = ColorStyle::front(CONFIGURATION.reached_color());
                 let ts = ColorStyle::front(CONFIGURATION.todo_color());
                 let cs = ColorStyle::back(cursor_bg());
@@ -126,29 +196,117 @@ where
                     day_style = day_style.combine(cs);
                     fs = fs.combine(cs);
                 }
-                let coords: Vec2 = ((i % 7) * 3, i / 7 + 2).into();
                 if let Some(c) = self.get_by_date(d) {
                     printer.with_style(day_style, |p| {
                         p.print(coords, &format!("{:^3}", c));
                     });
+                } else if self.reached_goal(d) {
+                    // untracked days can still meet the goal, see `Addiction`
+                    printer.with_style(day_style, |p| {
+                        p.print(coords, &format!("{:^3}", CONFIGURATION.look.future_chr));
+                    });
                 } else {
                     printer.with_style(fs, |p| {
                         p.print(coords, &format!("{:^3}", CONFIGURATION.look.future_chr));
                     });
                 }
-                i += 1;
+            }
+        };
+
+        let draw_month = |printer: &Printer| {
+            // one vertical bar per month, for the 12 months ending at the cursor
+            let levels = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+            let rows = 4;
+            for (idx, offset) in (0..12).rev().enumerate() {
+                let months = year * 12 + month as i32 - 1 - offset;
+                let (y, m) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+                let days = month_days(y, m);
+                let (completions, total) = progress(self, &days);
+                let filled = (completions * rows * 8).checked_div(total).unwrap_or(0);
+                let x = idx * 2;
+                for row in 0..rows {
+                    let fill = filled.saturating_sub(row * 8).min(8) as usize;
+                    let line_nr = 1 + rows as usize - row as usize;
+                    if fill > 0 {
+                        printer.with_style(goal_reached_style, |p| {
+                            p.print((x, line_nr), &levels[fill].to_string());
+                        });
+                    } else if row == 0 {
+                        printer.with_style(future_style, |p| p.print((x, line_nr), "▁"));
+                    }
+                }
+                let mut label_style = if m == month {
+                    Style::none()
+                } else {
+                    future_style
+                };
+                if m == month && printer.focused {
+                    label_style = label_style.combine(ColorStyle::back(cursor_bg()));
+                }
+                let label = NaiveDate::from_ymd_opt(y, m, 1).unwrap().format("%b");
+                printer.with_style(label_style, |p| {
+                    p.print((x, rows as usize + 2), &label.to_string()[..1]);
+                });
+            }
+        };
+
+        let draw_year = |printer: &Printer| {
+            // a heatmap of the year, one row per quarter and one
+            // cell per calendar week of every month, weeks shared
+            // by two months only count the days of each month
+            let shades = ['░', '▒', '▓', '█'];
+            let today = Local::now().naive_local().date();
+            for quarter in 0..4 {
+                let line_nr = quarter + 2;
+                printer.with_style(future_style, |p| {
+                    p.print((0, line_nr), &format!("Q{}", quarter + 1));
+                });
+                let mut x = 3;
+                for m in (1..=3).map(|i| quarter as u32 * 3 + i) {
+                    for week in month_weeks(year, m) {
+                        let week = week
+                            .into_iter()
+                            .filter(|d| d.month() == m)
+                            .collect::<Vec<_>>();
+                        let mut style = if week[0] > today {
+                            future_style
+                        } else {
+                            goal_reached_style
+                        };
+                        let (completions, total) = progress(self, &week);
+                        let cell = if week[0] > today || completions == 0 {
+                            CONFIGURATION.look.future_chr
+                        } else {
+                            shades[((completions * 4 - 1) / total).min(3) as usize]
+                        };
+                        // weeks without an active day, such as those before
+                        // the habit was created, are neither reached nor missed
+                        if total == 0 {
+                            style = future_style;
+                        } else if completions == 0 && week[0] <= today {
+                            style = Style::from(ColorStyle::front(CONFIGURATION.todo_color()));
+                        }
+                        if week.contains(&now) && printer.focused {
+                            style = style.combine(ColorStyle::back(cursor_bg()));
+                        }
+                        printer.with_style(style, |p| p.print((x, line_nr), &cell.to_string()));
+                        x += 1;
+                    }
+                    x += 1;
+                }
             }
         };
 
         match self.inner_data_ref().view_mode() {
             ViewMode::Day => draw_day(printer),
             ViewMode::Week => draw_week(printer),
-            _ => draw_day(printer),
+            ViewMode::Month => draw_month(printer),
+            ViewMode::Year => draw_year(printer),
         };
     }
 
     fn required_size(&mut self, _: Vec2) -> Vec2 {
-        (25, 6).into()
+        (VIEW_WIDTH, VIEW_HEIGHT).into()
     }
 
     fn take_focus(&mut self, _: Direction) -> Result<EventResult, CannotFocus> {
@@ -160,12 +318,21 @@ where
         if self.is_auto() {
             return EventResult::Ignored;
         }
+        let keybinds = &CONFIGURATION.keybinds;
         match e {
-            Event::Key(Key::Enter) | Event::Char('n') => {
+            Event::Key(Key::Enter) => {
+                self.modify(now, TrackEvent::Increment);
+                return EventResult::Consumed(None);
+            }
+            Event::Key(Key::Backspace) => {
+                self.modify(now, TrackEvent::Decrement);
+                return EventResult::Consumed(None);
+            }
+            e if keybinds.increment == e => {
                 self.modify(now, TrackEvent::Increment);
                 return EventResult::Consumed(None);
             }
-            Event::Key(Key::Backspace) | Event::Char('p') => {
+            e if keybinds.decrement == e => {
                 self.modify(now, TrackEvent::Decrement);
                 return EventResult::Consumed(None);
             }
@@ -201,4 +368,4 @@ macro_rules! generate_view_impls {
     );
 }
 
-generate_view_impls!(Count, Bit, Float);
+generate_view_impls!(Count, Bit, Float, Addiction);